@@ -1,9 +1,22 @@
-use crate::{graphics::ScalingMode, input::InputContext, EngineError};
+use crate::{
+    graphics::{ActiveTransition, Blending, Camera, Canvas, Color, DrawQueue, Image, ScalingMode},
+    input::{keyboard::Key, InputContext},
+    math::{Rectangle, Vec2},
+    EngineError,
+};
+
 pub struct Context {
     pub(crate) running: bool,
     pub(crate) debug: bool,
     pub(crate) input: InputContext,
     pub(crate) scaling: Scaling,
+    pub(crate) canvas: Option<Canvas>,
+    pub(crate) camera: Camera,
+    pub(crate) blending: Blending,
+    pub(crate) draw_queue: DrawQueue,
+    pub(crate) screenshot_key: Option<Key>,
+    pub(crate) screenshot: Option<Result<Image, crate::EngineError>>,
+    pub(crate) transition: Option<ActiveTransition>,
 }
 
 impl Context {
@@ -29,12 +42,19 @@ impl Context {
 }
 
 impl Context {
-    pub(crate) fn new() -> Result<Self, EngineError> {
+    pub(crate) fn new(size: Vec2) -> Result<Self, EngineError> {
         Ok(Self {
             running: true,
             debug: cfg!(debug_assertions),
             input: InputContext::new()?,
-            scaling: Default::default(),
+            scaling: Scaling::new(size),
+            canvas: None,
+            camera: Default::default(),
+            blending: Default::default(),
+            draw_queue: Default::default(),
+            screenshot_key: None,
+            screenshot: None,
+            transition: None,
         })
     }
 }
@@ -49,8 +69,18 @@ pub struct ContextBuilder<T: Into<String>> {
     pub height: i32,
 }
 
-#[derive(Default)]
-pub(crate) struct Scaling(ScalingMode, Option<f32>);
+pub(crate) struct Scaling {
+    pub mode: ScalingMode,
+    pub scale: Option<f32>,
+    /// Native resolution of the game
+    pub size: Vec2,
+    /// Size of the screen drawn to with the current scaling mode
+    pub screen: Vec2,
+    pub letterbox: Color,
+    /// Window size the scaling mode was last applied at
+    pub window: Vec2,
+    pub viewport: Rectangle,
+}
 
 impl UserContext for () {
     #[allow(unused_variables)]
@@ -80,8 +110,16 @@ impl<T: Into<String>> From<ContextBuilder<T>> for macroquad::prelude::Conf {
     }
 }
 
-impl From<(ScalingMode, Option<f32>)> for Scaling {
-    fn from(s: (ScalingMode, Option<f32>)) -> Self {
-        Self(s.0, s.1)
+impl Scaling {
+    pub fn new(size: Vec2) -> Self {
+        Self {
+            mode: Default::default(),
+            scale: None,
+            size,
+            screen: size,
+            letterbox: Color::BLACK,
+            window: Vec2::ZERO,
+            viewport: Rectangle::new(0.0, 0.0, size.x, size.y),
+        }
     }
 }
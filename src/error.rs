@@ -4,6 +4,11 @@ pub use image::ImageError;
 pub enum EngineError {
     Image(image::ImageError),
     File(FileError),
+    Parse(ParseError),
+    Font(macroquad::text::FontError),
+    Shader(macroquad::prelude::ShaderError),
+    /// Reading images of the format was not enabled when the engine was compiled.
+    UnsupportedFormat(crate::graphics::ImageFormat),
     #[cfg(all(not(target_arch = "wasm32"), feature = "gamepad"))]
     Gamepad(gilrs::Error),
 }
@@ -12,6 +17,7 @@ pub enum EngineError {
 pub enum FileError {
     Engine(macroquad::prelude::FileError),
     String(std::string::FromUtf8Error),
+    Io(std::io::Error),
 }
 
 impl std::error::Error for EngineError {}
@@ -21,12 +27,40 @@ impl std::fmt::Display for EngineError {
         match self {
             EngineError::Image(err) => std::fmt::Display::fmt(err, f),
             EngineError::File(err) => std::fmt::Display::fmt(err, f),
+            EngineError::Parse(err) => std::fmt::Display::fmt(err, f),
+            EngineError::Font(err) => std::fmt::Display::fmt(err, f),
+            EngineError::Shader(err) => std::fmt::Display::fmt(err, f),
+            EngineError::UnsupportedFormat(format) => match format.feature() {
+                Some(feature) => write!(
+                    f,
+                    "Could not read {} image, as the \"{}\" feature of the engine is not enabled",
+                    format, feature
+                ),
+                None => write!(f, "Could not read {} image", format),
+            },
             #[cfg(all(not(target_arch = "wasm32"), feature = "gamepad"))]
             EngineError::Gamepad(err) => std::fmt::Display::fmt(err, f),
         }
     }
 }
 
+/// An error found while reading a data file.
+#[derive(Debug)]
+pub struct ParseError {
+    /// The kind of file being read.
+    pub format: &'static str,
+    pub message: String,
+}
+
+impl ParseError {
+    pub fn new(format: &'static str, message: impl Into<String>) -> Self {
+        Self {
+            format,
+            message: message.into(),
+        }
+    }
+}
+
 impl std::error::Error for FileError {}
 
 impl std::fmt::Display for FileError {
@@ -34,10 +68,19 @@ impl std::fmt::Display for FileError {
         match self {
             FileError::Engine(err) => std::fmt::Display::fmt(err, f),
             FileError::String(err) => std::fmt::Display::fmt(err, f),
+            FileError::Io(err) => std::fmt::Display::fmt(err, f),
         }
     }
 }
 
+impl std::error::Error for ParseError {}
+
+impl std::fmt::Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Could not parse {}: {}", self.format, self.message)
+    }
+}
+
 impl From<macroquad::prelude::FileError> for FileError {
     fn from(err: macroquad::prelude::FileError) -> Self {
         Self::Engine(err)
@@ -49,3 +92,9 @@ impl From<image::ImageError> for EngineError {
         Self::Image(e)
     }
 }
+
+impl From<ParseError> for EngineError {
+    fn from(e: ParseError) -> Self {
+        Self::Parse(e)
+    }
+}
@@ -1,13 +1,55 @@
 pub(self) use crate::Context;
 
+mod animation;
+#[cfg(feature = "aseprite")]
+mod aseprite;
+mod atlas;
+mod batch;
+mod blend;
+mod camera;
+mod canvas;
+mod color;
 mod image;
+mod lighting;
+mod markup;
+mod mesh;
+mod nineslice;
+mod particles;
+mod queue;
+mod screenshot;
+mod shader;
 mod shapes;
+mod text;
 mod texture;
+#[cfg(feature = "tiled")]
+mod tiled;
+mod tilemap;
+mod transition;
 mod window;
 
+pub use self::animation::*;
+#[cfg(feature = "aseprite")]
+pub use self::aseprite::*;
+pub use self::atlas::*;
+pub use self::batch::*;
+pub use self::blend::*;
+pub use self::camera::*;
+pub use self::canvas::*;
+pub use self::color::*;
 pub use self::image::*;
+pub use self::lighting::*;
+pub use self::markup::*;
+pub use self::mesh::*;
+pub use self::nineslice::*;
+pub use self::particles::*;
+pub use self::queue::*;
+pub use self::screenshot::*;
+pub use self::shader::*;
 pub use self::shapes::*;
+pub use self::text::*;
 pub use self::texture::*;
+pub use self::tilemap::*;
+pub use self::transition::*;
 pub use self::window::*;
 
 pub type Color = macroquad::prelude::Color;
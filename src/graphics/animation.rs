@@ -0,0 +1,456 @@
+use crate::{context::Context, math::Rectangle, utils::HashMap};
+
+use super::{DrawParams, Texture};
+
+/// A texture split into a grid of equally sized frames.
+#[derive(Debug, Clone)]
+pub struct SpriteSheet {
+    texture: Texture,
+    frames: Vec<Rectangle>,
+}
+
+/// How an animation continues once its last frame has finished.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlayMode {
+    /// Start again from the first frame.
+    Loop,
+    /// Play backwards to the first frame, then forwards again.
+    PingPong,
+    /// Stay on the last frame.
+    Once,
+}
+
+/// A single frame of an animation clip.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Frame {
+    pub source: Rectangle,
+    /// How long the frame is shown for, in seconds.
+    /// Frames with no duration are held until another clip is played.
+    pub duration: f32,
+    /// Event emitted when the frame is shown.
+    pub event: Option<String>,
+}
+
+/// A named sequence of frames.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Clip {
+    pub frames: Vec<Frame>,
+    pub mode: PlayMode,
+}
+
+/// Plays animation clips, advancing with the time passed to `State::update`.
+#[derive(Debug, Default, Clone)]
+pub struct Animator {
+    clips: HashMap<String, Clip>,
+    current: Option<String>,
+    frame: usize,
+    time: f32,
+    reverse: bool,
+    finished: bool,
+    events: Vec<String>,
+}
+
+impl SpriteSheet {
+    /// Split a texture into frames of the given size, ordered left to right, top to bottom.
+    pub fn new(texture: Texture, frame_width: f32, frame_height: f32) -> Self {
+        Self::with_spacing(texture, frame_width, frame_height, 0.0, 0.0)
+    }
+
+    /// Split a texture into frames, skipping `margin` pixels around the texture's edges
+    /// and `spacing` pixels between each frame.
+    pub fn with_spacing(
+        texture: Texture,
+        frame_width: f32,
+        frame_height: f32,
+        margin: f32,
+        spacing: f32,
+    ) -> Self {
+        let step = |size: f32, frame: f32| {
+            if frame <= 0.0 {
+                0
+            } else {
+                ((size - margin * 2.0 + spacing) / (frame + spacing)).floor() as usize
+            }
+        };
+        let columns = step(texture.width(), frame_width);
+        let rows = step(texture.height(), frame_height);
+
+        let frames = (0..rows)
+            .flat_map(|row| {
+                (0..columns).map(move |column| {
+                    Rectangle::new(
+                        margin + column as f32 * (frame_width + spacing),
+                        margin + row as f32 * (frame_height + spacing),
+                        frame_width,
+                        frame_height,
+                    )
+                })
+            })
+            .collect();
+
+        Self { texture, frames }
+    }
+
+    pub fn frame(&self, index: usize) -> Option<Rectangle> {
+        self.frames.get(index).copied()
+    }
+
+    pub fn frames(&self) -> &[Rectangle] {
+        &self.frames
+    }
+
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    pub fn texture(&self) -> &Texture {
+        &self.texture
+    }
+
+    /// Draws a frame of the sprite sheet, overriding the source of the draw parameters.
+    pub fn draw(&self, ctx: &mut Context, frame: usize, x: f32, y: f32, params: DrawParams) {
+        if let Some(source) = self.frame(frame) {
+            self.texture.draw(
+                ctx,
+                x,
+                y,
+                DrawParams {
+                    source: Some(source),
+                    ..params
+                },
+            )
+        }
+    }
+
+    /// Creates a clip from frames of the sprite sheet that each last `duration` seconds.
+    /// Frame indices outside of the sprite sheet are skipped.
+    pub fn clip(
+        &self,
+        frames: impl IntoIterator<Item = usize>,
+        duration: f32,
+        mode: PlayMode,
+    ) -> Clip {
+        Clip::new(
+            frames
+                .into_iter()
+                .flat_map(|index| self.frame(index))
+                .map(|source| Frame::new(source, duration)),
+            mode,
+        )
+    }
+}
+
+impl Frame {
+    pub fn new(source: Rectangle, duration: f32) -> Self {
+        Self {
+            source,
+            duration,
+            event: None,
+        }
+    }
+}
+
+impl Clip {
+    pub fn new(frames: impl IntoIterator<Item = Frame>, mode: PlayMode) -> Self {
+        Self {
+            frames: frames.into_iter().collect(),
+            mode,
+        }
+    }
+
+    /// Sets the event emitted when the given frame is shown.
+    pub fn with_event(mut self, frame: usize, event: impl Into<String>) -> Self {
+        if let Some(frame) = self.frames.get_mut(frame) {
+            frame.event = Some(event.into());
+        }
+        self
+    }
+
+    /// Length of one play through of the clip in seconds.
+    pub fn duration(&self) -> f32 {
+        self.frames.iter().map(|frame| frame.duration).sum()
+    }
+}
+
+impl Animator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add(&mut self, name: impl Into<String>, clip: Clip) {
+        self.clips.insert(name.into(), clip);
+    }
+
+    pub fn with(mut self, name: impl Into<String>, clip: Clip) -> Self {
+        self.add(name, clip);
+        self
+    }
+
+    pub fn clip(&self, name: &str) -> Option<&Clip> {
+        self.clips.get(name)
+    }
+
+    /// Plays a clip from its beginning unless it is already playing.
+    /// Returns false if there is no clip with the name.
+    pub fn play(&mut self, name: &str) -> bool {
+        if self.current.as_deref() == Some(name) {
+            return true;
+        }
+        if !self.clips.contains_key(name) {
+            return false;
+        }
+        self.current = Some(name.to_owned());
+        self.restart();
+        true
+    }
+
+    /// Plays the current clip again from its first frame.
+    pub fn restart(&mut self) {
+        self.frame = 0;
+        self.time = 0.0;
+        self.reverse = false;
+        self.finished = false;
+        self.emit();
+    }
+
+    pub fn stop(&mut self) {
+        self.current = None;
+    }
+
+    pub fn current(&self) -> Option<&str> {
+        self.current.as_deref()
+    }
+
+    pub fn update(&mut self, delta: f32) {
+        let clip = match self.current.as_ref().and_then(|name| self.clips.get(name)) {
+            Some(clip) => clip,
+            None => return,
+        };
+
+        if self.finished || clip.frames.is_empty() {
+            return;
+        }
+
+        self.time += delta;
+
+        while let Some(frame) = clip.frames.get(self.frame) {
+            if frame.duration <= 0.0 || self.time < frame.duration {
+                break;
+            }
+
+            let last = clip.frames.len() - 1;
+
+            let next = match clip.mode {
+                PlayMode::Loop => Some(if self.frame == last {
+                    0
+                } else {
+                    self.frame + 1
+                }),
+                PlayMode::Once => (self.frame != last).then(|| self.frame + 1),
+                PlayMode::PingPong => {
+                    if last == 0 {
+                        Some(0)
+                    } else {
+                        if (self.reverse && self.frame == 0)
+                            || (!self.reverse && self.frame == last)
+                        {
+                            self.reverse = !self.reverse;
+                        }
+                        Some(match self.reverse {
+                            true => self.frame - 1,
+                            false => self.frame + 1,
+                        })
+                    }
+                }
+            };
+
+            match next {
+                Some(next) => {
+                    self.time -= frame.duration;
+                    self.frame = next;
+                    if let Some(event) = &clip.frames[next].event {
+                        self.events.push(event.clone());
+                    }
+                }
+                None => {
+                    self.time = 0.0;
+                    self.finished = true;
+                    break;
+                }
+            }
+        }
+    }
+
+    /// Index of the current frame in the current clip.
+    pub fn frame(&self) -> usize {
+        self.frame
+    }
+
+    /// The source rectangle of the current frame.
+    pub fn source(&self) -> Option<Rectangle> {
+        self.current
+            .as_ref()
+            .and_then(|name| self.clips.get(name))
+            .and_then(|clip| clip.frames.get(self.frame))
+            .map(|frame| frame.source)
+    }
+
+    /// Draw parameters with the source set to the current frame.
+    pub fn params(&self, params: DrawParams) -> DrawParams {
+        DrawParams {
+            source: self.source().or(params.source),
+            ..params
+        }
+    }
+
+    /// Whether a clip played with [PlayMode::Once] has reached its end.
+    pub fn finished(&self) -> bool {
+        self.finished
+    }
+
+    /// Takes the events of frames shown since this was last called.
+    pub fn events(&mut self) -> impl Iterator<Item = String> + '_ {
+        self.events.drain(..)
+    }
+
+    fn emit(&mut self) {
+        if let Some(event) = self
+            .current
+            .as_ref()
+            .and_then(|name| self.clips.get(name))
+            .and_then(|clip| clip.frames.get(self.frame))
+            .and_then(|frame| frame.event.clone())
+        {
+            self.events.push(event);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn clip(frames: usize, mode: PlayMode) -> Clip {
+        Clip::new(
+            (0..frames).map(|frame| Frame::new(Rectangle::new(frame as f32, 0.0, 1.0, 1.0), 1.0)),
+            mode,
+        )
+    }
+
+    fn frames(animator: &mut Animator, steps: usize, delta: f32) -> Vec<usize> {
+        (0..steps)
+            .map(|_| {
+                animator.update(delta);
+                animator.frame()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn loops() {
+        let mut animator = Animator::new().with("walk", clip(3, PlayMode::Loop));
+        assert!(animator.play("walk"));
+        assert_eq!(animator.frame(), 0);
+        assert_eq!(frames(&mut animator, 5, 1.0), [1, 2, 0, 1, 2]);
+        assert!(!animator.finished());
+    }
+
+    #[test]
+    fn ping_pongs() {
+        let mut animator = Animator::new().with("walk", clip(3, PlayMode::PingPong));
+        animator.play("walk");
+        assert_eq!(frames(&mut animator, 6, 1.0), [1, 2, 1, 0, 1, 2]);
+
+        let mut single = Animator::new().with("idle", clip(1, PlayMode::PingPong));
+        single.play("idle");
+        assert_eq!(frames(&mut single, 2, 1.0), [0, 0]);
+    }
+
+    #[test]
+    fn plays_once() {
+        let mut animator = Animator::new().with("attack", clip(3, PlayMode::Once));
+        animator.play("attack");
+        assert_eq!(frames(&mut animator, 2, 1.0), [1, 2]);
+        assert!(!animator.finished());
+        animator.update(1.0);
+        assert_eq!(animator.frame(), 2);
+        assert!(animator.finished());
+        animator.restart();
+        assert_eq!(animator.frame(), 0);
+        assert!(!animator.finished());
+    }
+
+    #[test]
+    fn large_delta_skips_frames() {
+        let mut animator = Animator::new().with("walk", clip(3, PlayMode::Loop));
+        animator.play("walk");
+        animator.update(7.5);
+        assert_eq!(animator.frame(), 1);
+        animator.update(0.5);
+        assert_eq!(animator.frame(), 2);
+
+        let mut once = Animator::new().with("attack", clip(3, PlayMode::Once));
+        once.play("attack");
+        once.update(100.0);
+        assert_eq!(once.frame(), 2);
+        assert!(once.finished());
+    }
+
+    #[test]
+    fn held_frame() {
+        let mut clip = clip(2, PlayMode::Loop);
+        clip.frames[1].duration = 0.0;
+        let mut animator = Animator::new().with("hold", clip);
+        animator.play("hold");
+        animator.update(10.0);
+        assert_eq!(animator.frame(), 1);
+    }
+
+    #[test]
+    fn events() {
+        let clip = clip(3, PlayMode::Loop)
+            .with_event(0, "start")
+            .with_event(2, "step");
+        let mut animator = Animator::new().with("walk", clip);
+        animator.play("walk");
+        assert_eq!(animator.events().collect::<Vec<_>>(), ["start"]);
+
+        animator.update(1.0);
+        assert_eq!(animator.events().count(), 0);
+
+        // every frame passed in one update emits its event
+        animator.update(2.0);
+        assert_eq!(animator.events().collect::<Vec<_>>(), ["step", "start"]);
+
+        // playing the current clip does not restart it
+        animator.play("walk");
+        assert_eq!(animator.events().count(), 0);
+    }
+
+    #[test]
+    fn play() {
+        let mut animator = Animator::new()
+            .with("walk", clip(3, PlayMode::Loop))
+            .with("idle", clip(2, PlayMode::Loop));
+        assert!(!animator.play("run"));
+        assert_eq!(animator.current(), None);
+        assert_eq!(animator.source(), None);
+
+        animator.play("walk");
+        animator.update(1.0);
+        assert_eq!(animator.source(), Some(Rectangle::new(1.0, 0.0, 1.0, 1.0)));
+
+        animator.play("idle");
+        assert_eq!(animator.current(), Some("idle"));
+        assert_eq!(animator.frame(), 0);
+
+        animator.stop();
+        animator.update(1.0);
+        assert_eq!(animator.source(), None);
+    }
+}
@@ -0,0 +1,1216 @@
+//! Loading of Aseprite files and Aseprite's JSON sprite sheet export.
+
+use serde::Deserialize;
+
+use crate::{
+    context::Context,
+    error::ParseError,
+    math::{vec2, Rectangle, Vec2},
+};
+
+use super::{image::blend, Animator, Clip, Frame, Image, PlayMode, Texture};
+
+const FORMAT: &str = "aseprite file";
+
+/// Most colors a palette can hold.
+const PALETTE_SIZE: usize = 256;
+
+/// Largest width or height of the sheet all frames are packed into.
+const MAX_SHEET_SIZE: u32 = 16384;
+
+/// A sprite loaded from Aseprite, with all frames packed into one sheet.
+#[derive(Debug, Clone)]
+pub struct Aseprite {
+    /// Size of the sprite's canvas.
+    pub width: u32,
+    pub height: u32,
+    /// Image holding every frame of the sprite.
+    pub sheet: Image,
+    pub frames: Vec<AsepriteFrame>,
+    pub tags: Vec<AsepriteTag>,
+    pub layers: Vec<AsepriteLayer>,
+    pub slices: Vec<AsepriteSlice>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AsepriteFrame {
+    /// Area of the sheet the frame is in.
+    pub source: Rectangle,
+    /// Position of the frame on the sprite's canvas, if it was trimmed when exported.
+    pub offset: Vec2,
+    /// Duration of the frame in seconds.
+    pub duration: f32,
+}
+
+/// A named range of frames, usually an animation.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AsepriteTag {
+    pub name: String,
+    pub from: usize,
+    pub to: usize,
+    pub direction: AsepriteDirection,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AsepriteDirection {
+    Forward,
+    Reverse,
+    PingPong,
+    PingPongReverse,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AsepriteLayer {
+    pub name: String,
+    pub visible: bool,
+    pub opacity: u8,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AsepriteSlice {
+    pub name: String,
+    pub keys: Vec<AsepriteSliceKey>,
+}
+
+/// Bounds of a slice starting at a frame.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AsepriteSliceKey {
+    pub frame: usize,
+    pub bounds: Rectangle,
+    /// Center area of a nine-slice, relative to the bounds.
+    pub center: Option<Rectangle>,
+    pub pivot: Option<Vec2>,
+}
+
+impl Aseprite {
+    /// Reads an `.aseprite` or `.ase` file.
+    ///
+    /// Visible layers are merged into each frame.
+    /// Layer blend modes other than normal are drawn as normal.
+    pub fn new(data: &[u8]) -> Result<Self, ParseError> {
+        binary::read(data)
+    }
+
+    /// Reads a sprite sheet exported by Aseprite as JSON, along with the sheet image.
+    /// Both the hash and array layouts of the frame list are supported.
+    pub fn from_json(json: &str, sheet: Image) -> Result<Self, ParseError> {
+        json::read(json, sheet)
+    }
+
+    pub fn texture(&self, ctx: &mut Context) -> Texture {
+        Texture::from_image(ctx, &self.sheet)
+    }
+
+    pub fn tag(&self, name: &str) -> Option<&AsepriteTag> {
+        self.tags.iter().find(|tag| tag.name == name)
+    }
+
+    pub fn slice(&self, name: &str) -> Option<&AsepriteSlice> {
+        self.slices.iter().find(|slice| slice.name == name)
+    }
+
+    /// Creates an animation clip from a tag.
+    pub fn clip(&self, tag: &AsepriteTag) -> Clip {
+        let frames = self
+            .frames
+            .get(tag.from..=tag.to.min(self.frames.len().saturating_sub(1)))
+            .unwrap_or_default()
+            .iter()
+            .map(|frame| Frame::new(frame.source, frame.duration));
+
+        match tag.direction {
+            AsepriteDirection::Forward => Clip::new(frames, PlayMode::Loop),
+            AsepriteDirection::Reverse => Clip::new(frames.rev(), PlayMode::Loop),
+            AsepriteDirection::PingPong => Clip::new(frames, PlayMode::PingPong),
+            AsepriteDirection::PingPongReverse => Clip::new(frames.rev(), PlayMode::PingPong),
+        }
+    }
+
+    /// Creates an animator with a clip for every tag.
+    pub fn animator(&self) -> Animator {
+        self.tags.iter().fold(Animator::new(), |animator, tag| {
+            animator.with(tag.name.clone(), self.clip(tag))
+        })
+    }
+}
+
+impl AsepriteSlice {
+    /// The key used at a frame.
+    pub fn key(&self, frame: usize) -> Option<&AsepriteSliceKey> {
+        self.keys.iter().rev().find(|key| key.frame <= frame)
+    }
+}
+
+mod binary {
+
+    use super::*;
+
+    struct Reader<'a> {
+        data: &'a [u8],
+        position: usize,
+    }
+
+    impl<'a> Reader<'a> {
+        fn bytes(&mut self, len: usize) -> Result<&'a [u8], ParseError> {
+            let bytes = self
+                .position
+                .checked_add(len)
+                .and_then(|end| self.data.get(self.position..end))
+                .ok_or_else(|| ParseError::new(FORMAT, "unexpected end of file"))?;
+            self.position += len;
+            Ok(bytes)
+        }
+
+        fn byte(&mut self) -> Result<u8, ParseError> {
+            Ok(self.bytes(1)?[0])
+        }
+
+        fn word(&mut self) -> Result<u16, ParseError> {
+            let b = self.bytes(2)?;
+            Ok(u16::from_le_bytes([b[0], b[1]]))
+        }
+
+        fn short(&mut self) -> Result<i16, ParseError> {
+            Ok(self.word()? as i16)
+        }
+
+        fn dword(&mut self) -> Result<u32, ParseError> {
+            let b = self.bytes(4)?;
+            Ok(u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        }
+
+        fn long(&mut self) -> Result<i32, ParseError> {
+            Ok(self.dword()? as i32)
+        }
+
+        fn string(&mut self) -> Result<String, ParseError> {
+            let len = self.word()? as usize;
+            Ok(String::from_utf8_lossy(self.bytes(len)?).into_owned())
+        }
+
+        fn skip(&mut self, len: usize) -> Result<(), ParseError> {
+            self.bytes(len).map(|_| ())
+        }
+
+        /// Bytes left before the end of a chunk.
+        fn left(&self, chunk_end: usize) -> usize {
+            chunk_end.min(self.data.len()).saturating_sub(self.position)
+        }
+    }
+
+    struct Layer {
+        layer: AsepriteLayer,
+        level: u16,
+        group: bool,
+    }
+
+    #[derive(Clone)]
+    struct Cel {
+        x: i32,
+        y: i32,
+        opacity: u8,
+        image: image::RgbaImage,
+    }
+
+    pub fn read(data: &[u8]) -> Result<Aseprite, ParseError> {
+        let mut reader = Reader { data, position: 0 };
+
+        reader.skip(4)?;
+        if reader.word()? != 0xA5E0 {
+            return Err(ParseError::new(FORMAT, "not an aseprite file"));
+        }
+        let frame_count = reader.word()? as usize;
+        let width = reader.word()? as u32;
+        let height = reader.word()? as u32;
+        let depth = reader.word()?;
+        let flags = reader.dword()?;
+        reader.skip(2 + 4 + 4)?;
+        let transparent = reader.byte()?;
+        reader.skip(128 - 29)?;
+
+        let layer_opacity = flags & 1 != 0;
+        let (columns, rows) = grid(width, height, frame_count)?;
+
+        let mut layers: Vec<Layer> = Vec::new();
+        let mut palette = vec![[0u8; 4]; PALETTE_SIZE];
+        let mut tags = Vec::new();
+        let mut slices = Vec::new();
+        let mut durations = Vec::with_capacity(frame_count);
+        let mut cels: Vec<Vec<Option<Cel>>> = Vec::with_capacity(frame_count);
+
+        for _ in 0..frame_count {
+            let start = reader.position;
+            let size = reader.dword()? as usize;
+            if reader.word()? != 0xF1FA {
+                return Err(ParseError::new(FORMAT, "invalid frame header"));
+            }
+            let frame_end = section_end(start, size, 16, data.len())
+                .ok_or_else(|| ParseError::new(FORMAT, "invalid frame size"))?;
+            let old_chunks = reader.word()? as u32;
+            durations.push(reader.word()? as f32 / 1000.0);
+            reader.skip(2)?;
+            let chunks = match reader.dword()? {
+                0 => old_chunks,
+                chunks => chunks,
+            };
+
+            let mut frame_cels = Vec::new();
+
+            for _ in 0..chunks {
+                let chunk_start = reader.position;
+                let chunk_size = reader.dword()? as usize;
+                let chunk_end = section_end(chunk_start, chunk_size, 6, frame_end)
+                    .ok_or_else(|| ParseError::new(FORMAT, "invalid chunk size"))?;
+                let kind = reader.word()?;
+
+                match kind {
+                    0x0004 => {
+                        let packets = reader.word()?;
+                        let mut index = 0usize;
+                        for _ in 0..packets {
+                            index += reader.byte()? as usize;
+                            let count = match reader.byte()? {
+                                0 => 256,
+                                count => count as usize,
+                            };
+                            for _ in 0..count {
+                                let rgb = reader.bytes(3)?;
+                                if let Some(color) = palette.get_mut(index) {
+                                    *color = [rgb[0], rgb[1], rgb[2], 255];
+                                }
+                                index += 1;
+                            }
+                        }
+                    }
+                    0x2004 => {
+                        let flags = reader.word()?;
+                        let kind = reader.word()?;
+                        let level = reader.word()?;
+                        reader.skip(2 + 2 + 2)?;
+                        let opacity = reader.byte()?;
+                        reader.skip(3)?;
+                        let name = reader.string()?;
+                        layers.push(Layer {
+                            layer: AsepriteLayer {
+                                name,
+                                visible: flags & 1 != 0,
+                                opacity: if layer_opacity { opacity } else { 255 },
+                            },
+                            level,
+                            group: kind == 1,
+                        });
+                    }
+                    0x2005 => {
+                        let layer = reader.word()? as usize;
+                        let x = reader.short()? as i32;
+                        let y = reader.short()? as i32;
+                        let opacity = reader.byte()?;
+                        let kind = reader.word()?;
+                        reader.skip(2 + 5)?;
+
+                        let cel = match kind {
+                            0 | 2 => {
+                                let w = reader.word()? as u32;
+                                let h = reader.word()? as u32;
+                                let pixels = reader.bytes(reader.left(chunk_end))?;
+                                let pixels = match kind {
+                                    0 => pixels.to_vec(),
+                                    _ => decompress(pixels, w, h, depth)?,
+                                };
+                                Some(Cel {
+                                    x,
+                                    y,
+                                    opacity,
+                                    image: pixels_to_image(
+                                        w,
+                                        h,
+                                        depth,
+                                        &pixels,
+                                        &palette,
+                                        transparent,
+                                    )?,
+                                })
+                            }
+                            1 => {
+                                let linked = reader.word()? as usize;
+                                cels.get(linked)
+                                    .and_then(|cels| cels.get(layer))
+                                    .and_then(Option::as_ref)
+                                    .map(|cel| Cel {
+                                        x,
+                                        y,
+                                        opacity,
+                                        image: cel.image.clone(),
+                                    })
+                            }
+                            // tilemap cels are not supported
+                            _ => None,
+                        };
+
+                        if frame_cels.len() <= layer {
+                            frame_cels.resize(layer + 1, None);
+                        }
+                        frame_cels[layer] = cel;
+                    }
+                    0x2018 => {
+                        let count = reader.word()?;
+                        reader.skip(8)?;
+                        for _ in 0..count {
+                            let from = reader.word()? as usize;
+                            let to = reader.word()? as usize;
+                            let direction = match reader.byte()? {
+                                1 => AsepriteDirection::Reverse,
+                                2 => AsepriteDirection::PingPong,
+                                3 => AsepriteDirection::PingPongReverse,
+                                _ => AsepriteDirection::Forward,
+                            };
+                            reader.skip(2 + 6 + 3 + 1)?;
+                            let name = reader.string()?;
+                            tags.push(AsepriteTag {
+                                name,
+                                from,
+                                to,
+                                direction,
+                            });
+                        }
+                    }
+                    0x2019 => {
+                        reader.skip(4)?;
+                        let first = reader.dword()? as usize;
+                        let last = reader.dword()? as usize;
+                        reader.skip(8)?;
+                        if last >= PALETTE_SIZE {
+                            return Err(ParseError::new(
+                                FORMAT,
+                                format!("palette has more than {} colors", PALETTE_SIZE),
+                            ));
+                        }
+                        for color in palette.iter_mut().take(last + 1).skip(first) {
+                            let flags = reader.word()?;
+                            let rgba = reader.bytes(4)?;
+                            *color = [rgba[0], rgba[1], rgba[2], rgba[3]];
+                            if flags & 1 != 0 {
+                                reader.string()?;
+                            }
+                        }
+                    }
+                    0x2022 => {
+                        let count = reader.dword()?;
+                        let flags = reader.dword()?;
+                        reader.skip(4)?;
+                        let name = reader.string()?;
+                        let key_size = 20
+                            + if flags & 1 != 0 { 16 } else { 0 }
+                            + if flags & 2 != 0 { 8 } else { 0 };
+                        if count as usize > reader.left(chunk_end) / key_size {
+                            return Err(ParseError::new(
+                                FORMAT,
+                                "slice has more keys than fit in its chunk",
+                            ));
+                        }
+                        let mut keys = Vec::with_capacity(count as usize);
+                        for _ in 0..count {
+                            let frame = reader.dword()? as usize;
+                            let bounds = Rectangle::new(
+                                reader.long()? as f32,
+                                reader.long()? as f32,
+                                reader.dword()? as f32,
+                                reader.dword()? as f32,
+                            );
+                            let center = match flags & 1 != 0 {
+                                true => Some(Rectangle::new(
+                                    reader.long()? as f32,
+                                    reader.long()? as f32,
+                                    reader.dword()? as f32,
+                                    reader.dword()? as f32,
+                                )),
+                                false => None,
+                            };
+                            let pivot = match flags & 2 != 0 {
+                                true => Some(vec2(reader.long()? as f32, reader.long()? as f32)),
+                                false => None,
+                            };
+                            keys.push(AsepriteSliceKey {
+                                frame,
+                                bounds,
+                                center,
+                                pivot,
+                            });
+                        }
+                        slices.push(AsepriteSlice { name, keys });
+                    }
+                    _ => (),
+                }
+
+                reader.position = chunk_end;
+            }
+
+            reader.position = frame_end;
+            cels.push(frame_cels);
+        }
+
+        let visible = visible_layers(&layers);
+
+        let frames = cels
+            .iter()
+            .map(|cels| {
+                let mut image = image::RgbaImage::new(width, height);
+                for (index, cel) in cels.iter().enumerate() {
+                    if let (Some(cel), Some(opacity)) = (cel, visible.get(index).copied().flatten())
+                    {
+                        let opacity = cel.opacity as u32 * opacity as u32 / 255;
+                        draw_cel(&mut image, cel, opacity as u8);
+                    }
+                }
+                image
+            })
+            .collect::<Vec<_>>();
+
+        let (sheet, sources) = pack(width, height, columns, rows, &frames);
+
+        Ok(Aseprite {
+            width,
+            height,
+            sheet,
+            frames: sources
+                .into_iter()
+                .zip(durations)
+                .map(|(source, duration)| AsepriteFrame {
+                    source,
+                    offset: Vec2::ZERO,
+                    duration,
+                })
+                .collect(),
+            tags,
+            layers: layers.into_iter().map(|layer| layer.layer).collect(),
+            slices,
+        })
+    }
+
+    /// Returns where a frame or chunk of at least `min` bytes ends,
+    /// if it ends before `limit`.
+    fn section_end(start: usize, size: usize, min: usize, limit: usize) -> Option<usize> {
+        start
+            .checked_add(size)
+            .filter(|end| size >= min && *end <= limit)
+    }
+
+    /// Decompresses the pixels of a cel, stopping at the size of the cel.
+    fn decompress(
+        pixels: &[u8],
+        width: u32,
+        height: u32,
+        depth: u16,
+    ) -> Result<Vec<u8>, ParseError> {
+        let size = (width as usize)
+            .checked_mul(height as usize)
+            .and_then(|size| size.checked_mul(depth as usize / 8))
+            .ok_or_else(|| ParseError::new(FORMAT, "cel is too large"))?;
+        miniz_oxide::inflate::decompress_to_vec_zlib_with_limit(pixels, size)
+            .map_err(|err| ParseError::new(FORMAT, format!("could not decompress cel: {:?}", err)))
+    }
+
+    /// Returns the columns and rows of the grid frames are packed in,
+    /// making sure the sheet is not too large.
+    fn grid(width: u32, height: u32, frames: usize) -> Result<(u32, u32), ParseError> {
+        let columns = (frames as f32).sqrt().ceil().max(1.0) as u32;
+        let rows = (frames as f32 / columns as f32).ceil().max(1.0) as u32;
+        match width
+            .checked_mul(columns)
+            .zip(height.checked_mul(rows))
+            .filter(|(width, height)| *width <= MAX_SHEET_SIZE && *height <= MAX_SHEET_SIZE)
+        {
+            Some(..) => Ok((columns, rows)),
+            None => Err(ParseError::new(
+                FORMAT,
+                format!(
+                    "sprite sheet would be larger than {0}x{0} pixels",
+                    MAX_SHEET_SIZE
+                ),
+            )),
+        }
+    }
+
+    /// Returns the opacity of each layer that should be drawn,
+    /// hiding layers inside hidden groups.
+    fn visible_layers(layers: &[Layer]) -> Vec<Option<u8>> {
+        let mut hidden_below: Option<u16> = None;
+        layers
+            .iter()
+            .map(|layer| {
+                if let Some(level) = hidden_below {
+                    if layer.level > level {
+                        return None;
+                    }
+                    hidden_below = None;
+                }
+                if !layer.layer.visible {
+                    hidden_below = Some(layer.level);
+                    return None;
+                }
+                (!layer.group).then_some(layer.layer.opacity)
+            })
+            .collect()
+    }
+
+    fn pixels_to_image(
+        width: u32,
+        height: u32,
+        depth: u16,
+        pixels: &[u8],
+        palette: &[[u8; 4]],
+        transparent: u8,
+    ) -> Result<image::RgbaImage, ParseError> {
+        let rgba = match depth {
+            32 => pixels.to_vec(),
+            16 => pixels
+                .chunks_exact(2)
+                .flat_map(|p| [p[0], p[0], p[0], p[1]])
+                .collect(),
+            8 => pixels
+                .iter()
+                .flat_map(|index| match *index == transparent {
+                    true => [0; 4],
+                    false => palette.get(*index as usize).copied().unwrap_or([0; 4]),
+                })
+                .collect(),
+            depth => {
+                return Err(ParseError::new(
+                    FORMAT,
+                    format!("unsupported color depth {}", depth),
+                ))
+            }
+        };
+        image::RgbaImage::from_raw(width, height, rgba)
+            .ok_or_else(|| ParseError::new(FORMAT, "cel is smaller than its size"))
+    }
+
+    fn draw_cel(image: &mut image::RgbaImage, cel: &Cel, opacity: u8) {
+        for (x, y, src) in cel.image.enumerate_pixels() {
+            let (dx, dy) = (cel.x + x as i32, cel.y + y as i32);
+            if dx < 0 || dy < 0 || dx as u32 >= image.width() || dy as u32 >= image.height() {
+                continue;
+            }
+            let mut src = *src;
+            src[3] = (src[3] as u32 * opacity as u32 / 255) as u8;
+            let dst = image.get_pixel_mut(dx as u32, dy as u32);
+            *dst = blend(*dst, src);
+        }
+    }
+
+    /// Places frames on a grid in a single image.
+    fn pack(
+        width: u32,
+        height: u32,
+        columns: u32,
+        rows: u32,
+        frames: &[image::RgbaImage],
+    ) -> (Image, Vec<Rectangle>) {
+        let mut sheet = image::RgbaImage::new(width * columns, height * rows);
+        let sources = frames
+            .iter()
+            .enumerate()
+            .map(|(index, frame)| {
+                let (x, y) = (
+                    index as u32 % columns * width,
+                    index as u32 / columns * height,
+                );
+                image::imageops::replace(&mut sheet, frame, x, y);
+                Rectangle::new(x as _, y as _, width as _, height as _)
+            })
+            .collect();
+        (Image(sheet), sources)
+    }
+}
+
+mod json {
+
+    use super::*;
+
+    #[derive(Deserialize)]
+    struct Sheet {
+        frames: Frames,
+        meta: Meta,
+    }
+
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Frames {
+        Array(Vec<SheetFrame>),
+        Hash(FrameHash),
+    }
+
+    /// Frames keyed by name, kept in the order they are written in.
+    struct FrameHash(Vec<SheetFrame>);
+
+    impl<'de> Deserialize<'de> for FrameHash {
+        fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            struct Visitor;
+
+            impl<'de> serde::de::Visitor<'de> for Visitor {
+                type Value = FrameHash;
+
+                fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                    f.write_str("a map of frames")
+                }
+
+                fn visit_map<A: serde::de::MapAccess<'de>>(
+                    self,
+                    mut map: A,
+                ) -> Result<Self::Value, A::Error> {
+                    let mut frames = Vec::new();
+                    while let Some((serde::de::IgnoredAny, frame)) = map.next_entry()? {
+                        frames.push(frame);
+                    }
+                    Ok(FrameHash(frames))
+                }
+            }
+
+            deserializer.deserialize_map(Visitor)
+        }
+    }
+
+    #[derive(Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    struct SheetFrame {
+        frame: Rect,
+        #[serde(default)]
+        sprite_source_size: Option<Rect>,
+        source_size: Size,
+        #[serde(default)]
+        duration: u32,
+    }
+
+    #[derive(Deserialize)]
+    struct Rect {
+        x: i32,
+        y: i32,
+        w: u32,
+        h: u32,
+    }
+
+    #[derive(Deserialize)]
+    struct Size {
+        w: u32,
+        h: u32,
+    }
+
+    #[derive(Deserialize)]
+    struct Point {
+        x: i32,
+        y: i32,
+    }
+
+    #[derive(Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    struct Meta {
+        #[serde(default)]
+        frame_tags: Vec<Tag>,
+        #[serde(default)]
+        layers: Vec<Layer>,
+        #[serde(default)]
+        slices: Vec<Slice>,
+    }
+
+    #[derive(Deserialize)]
+    struct Tag {
+        name: String,
+        from: usize,
+        to: usize,
+        #[serde(default)]
+        direction: String,
+    }
+
+    #[derive(Deserialize)]
+    struct Layer {
+        name: String,
+        #[serde(default = "opaque")]
+        opacity: u8,
+    }
+
+    fn opaque() -> u8 {
+        255
+    }
+
+    #[derive(Deserialize)]
+    struct Slice {
+        name: String,
+        keys: Vec<SliceKey>,
+    }
+
+    #[derive(Deserialize)]
+    struct SliceKey {
+        frame: usize,
+        bounds: Rect,
+        center: Option<Rect>,
+        pivot: Option<Point>,
+    }
+
+    impl From<Rect> for Rectangle {
+        fn from(rect: Rect) -> Self {
+            Rectangle::new(rect.x as _, rect.y as _, rect.w as _, rect.h as _)
+        }
+    }
+
+    pub fn read(json: &str, sheet: Image) -> Result<Aseprite, ParseError> {
+        let data: Sheet =
+            serde_json::from_str(json).map_err(|err| ParseError::new(FORMAT, err.to_string()))?;
+
+        let frames = match data.frames {
+            Frames::Array(frames) => frames,
+            Frames::Hash(FrameHash(frames)) => frames,
+        };
+
+        let (width, height) = frames
+            .first()
+            .map(|frame| (frame.source_size.w, frame.source_size.h))
+            .unwrap_or_default();
+
+        Ok(Aseprite {
+            width,
+            height,
+            sheet,
+            frames: frames
+                .into_iter()
+                .map(|frame| AsepriteFrame {
+                    offset: frame
+                        .sprite_source_size
+                        .as_ref()
+                        .map(|rect| vec2(rect.x as _, rect.y as _))
+                        .unwrap_or(Vec2::ZERO),
+                    source: frame.frame.into(),
+                    duration: frame.duration as f32 / 1000.0,
+                })
+                .collect(),
+            tags: data
+                .meta
+                .frame_tags
+                .into_iter()
+                .map(|tag| AsepriteTag {
+                    name: tag.name,
+                    from: tag.from,
+                    to: tag.to,
+                    direction: match tag.direction.as_str() {
+                        "reverse" => AsepriteDirection::Reverse,
+                        "pingpong" => AsepriteDirection::PingPong,
+                        "pingpong_reverse" => AsepriteDirection::PingPongReverse,
+                        _ => AsepriteDirection::Forward,
+                    },
+                })
+                .collect(),
+            layers: data
+                .meta
+                .layers
+                .into_iter()
+                .map(|layer| AsepriteLayer {
+                    name: layer.name,
+                    visible: true,
+                    opacity: layer.opacity,
+                })
+                .collect(),
+            slices: data
+                .meta
+                .slices
+                .into_iter()
+                .map(|slice| AsepriteSlice {
+                    name: slice.name,
+                    keys: slice
+                        .keys
+                        .into_iter()
+                        .map(|key| AsepriteSliceKey {
+                            frame: key.frame,
+                            bounds: key.bounds.into(),
+                            center: key.center.map(Into::into),
+                            pivot: key.pivot.map(|p| vec2(p.x as _, p.y as _)),
+                        })
+                        .collect(),
+                })
+                .collect(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn header(frames: u16, width: u16, height: u16, depth: u16) -> Vec<u8> {
+        let mut data = Vec::new();
+        data.extend(0u32.to_le_bytes());
+        data.extend(0xA5E0u16.to_le_bytes());
+        data.extend(frames.to_le_bytes());
+        data.extend(width.to_le_bytes());
+        data.extend(height.to_le_bytes());
+        data.extend(depth.to_le_bytes());
+        data.extend(1u32.to_le_bytes());
+        data.resize(28, 0);
+        // transparent palette index
+        data.push(0);
+        data.resize(128, 0);
+        data
+    }
+
+    fn frame(duration: u16, chunks: &[Vec<u8>]) -> Vec<u8> {
+        let body = chunks.concat();
+        let mut data = Vec::new();
+        data.extend((16 + body.len() as u32).to_le_bytes());
+        data.extend(0xF1FAu16.to_le_bytes());
+        data.extend((chunks.len() as u16).to_le_bytes());
+        data.extend(duration.to_le_bytes());
+        data.extend([0; 2]);
+        data.extend((chunks.len() as u32).to_le_bytes());
+        data.extend(body);
+        data
+    }
+
+    fn chunk(kind: u16, body: &[u8]) -> Vec<u8> {
+        let mut data = Vec::new();
+        data.extend((6 + body.len() as u32).to_le_bytes());
+        data.extend(kind.to_le_bytes());
+        data.extend(body);
+        data
+    }
+
+    fn string(value: &str) -> Vec<u8> {
+        let mut data = (value.len() as u16).to_le_bytes().to_vec();
+        data.extend(value.as_bytes());
+        data
+    }
+
+    fn layer(name: &str, visible: bool) -> Vec<u8> {
+        let mut body = Vec::new();
+        body.extend((visible as u16).to_le_bytes());
+        body.extend([0; 10]);
+        body.push(255);
+        body.extend([0; 3]);
+        body.extend(string(name));
+        chunk(0x2004, &body)
+    }
+
+    fn cel(layer: u16, x: i16, y: i16, kind: u16, data: &[u8]) -> Vec<u8> {
+        let mut body = Vec::new();
+        body.extend(layer.to_le_bytes());
+        body.extend(x.to_le_bytes());
+        body.extend(y.to_le_bytes());
+        body.push(255);
+        body.extend(kind.to_le_bytes());
+        body.extend([0; 7]);
+        body.extend(data);
+        chunk(0x2005, &body)
+    }
+
+    fn pixels(width: u16, height: u16, pixels: &[u8]) -> Vec<u8> {
+        let mut data = Vec::new();
+        data.extend(width.to_le_bytes());
+        data.extend(height.to_le_bytes());
+        data.extend(pixels);
+        data
+    }
+
+    fn file(header: Vec<u8>, frames: &[Vec<u8>]) -> Vec<u8> {
+        [header, frames.concat()].concat()
+    }
+
+    fn pixel(aseprite: &Aseprite, frame: usize, x: u32, y: u32) -> [u8; 4] {
+        let source = aseprite.frames[frame].source;
+        aseprite
+            .sheet
+            .0
+            .get_pixel(source.x as u32 + x, source.y as u32 + y)
+            .0
+    }
+
+    const RED: [u8; 4] = [255, 0, 0, 255];
+    const BLUE: [u8; 4] = [0, 0, 255, 255];
+
+    #[test]
+    fn reads_header() {
+        let data = file(header(1, 3, 2, 32), &[frame(100, &[])]);
+        let aseprite = Aseprite::new(&data).unwrap();
+        assert_eq!((aseprite.width, aseprite.height), (3, 2));
+        assert_eq!(aseprite.frames.len(), 1);
+        assert_eq!(aseprite.frames[0].duration, 0.1);
+        assert_eq!(
+            aseprite.frames[0].source,
+            Rectangle::new(0.0, 0.0, 3.0, 2.0)
+        );
+
+        let mut data = data;
+        data[4] = 0;
+        assert!(Aseprite::new(&data).is_err());
+    }
+
+    #[test]
+    fn raw_cel() {
+        let data = file(
+            header(1, 2, 2, 32),
+            &[frame(
+                100,
+                &[
+                    layer("background", true),
+                    cel(0, 1, 0, 0, &pixels(1, 2, &[RED, BLUE].concat())),
+                ],
+            )],
+        );
+        let aseprite = Aseprite::new(&data).unwrap();
+        assert_eq!(aseprite.layers[0].name, "background");
+        assert_eq!(pixel(&aseprite, 0, 0, 0), [0; 4]);
+        assert_eq!(pixel(&aseprite, 0, 1, 0), RED);
+        assert_eq!(pixel(&aseprite, 0, 1, 1), BLUE);
+    }
+
+    #[test]
+    fn linked_cel() {
+        let data = file(
+            header(2, 1, 1, 32),
+            &[
+                frame(
+                    100,
+                    &[layer("a", true), cel(0, 0, 0, 0, &pixels(1, 1, &RED))],
+                ),
+                frame(100, &[cel(0, 0, 0, 1, &0u16.to_le_bytes())]),
+            ],
+        );
+        let aseprite = Aseprite::new(&data).unwrap();
+        assert_eq!(pixel(&aseprite, 1, 0, 0), RED);
+    }
+
+    #[test]
+    fn compressed_cel() {
+        let compressed = miniz_oxide::deflate::compress_to_vec_zlib(&[RED, BLUE].concat(), 6);
+        let data = file(
+            header(1, 2, 1, 32),
+            &[frame(
+                100,
+                &[
+                    layer("a", true),
+                    cel(0, 0, 0, 2, &pixels(2, 1, &compressed)),
+                ],
+            )],
+        );
+        let aseprite = Aseprite::new(&data).unwrap();
+        assert_eq!(pixel(&aseprite, 0, 0, 0), RED);
+        assert_eq!(pixel(&aseprite, 0, 1, 0), BLUE);
+
+        let data = file(
+            header(1, 2, 1, 32),
+            &[frame(
+                100,
+                &[layer("a", true), cel(0, 0, 0, 2, &pixels(2, 1, &[1, 2, 3]))],
+            )],
+        );
+        assert!(Aseprite::new(&data).is_err());
+    }
+
+    #[test]
+    fn tilemap_cel_is_skipped() {
+        let data = file(
+            header(1, 1, 1, 32),
+            &[frame(100, &[layer("a", true), cel(0, 0, 0, 3, &[0; 32])])],
+        );
+        let aseprite = Aseprite::new(&data).unwrap();
+        assert_eq!(pixel(&aseprite, 0, 0, 0), [0; 4]);
+    }
+
+    #[test]
+    fn indexed_cel() {
+        let mut palette = Vec::new();
+        palette.extend(2u32.to_le_bytes());
+        palette.extend(0u32.to_le_bytes());
+        palette.extend(1u32.to_le_bytes());
+        palette.extend([0; 8]);
+        for color in [RED, BLUE] {
+            palette.extend(0u16.to_le_bytes());
+            palette.extend(color);
+        }
+        let data = file(
+            header(1, 2, 1, 8),
+            &[frame(
+                100,
+                &[
+                    chunk(0x2019, &palette),
+                    layer("a", true),
+                    cel(0, 0, 0, 0, &pixels(2, 1, &[0, 1])),
+                ],
+            )],
+        );
+        let aseprite = Aseprite::new(&data).unwrap();
+        // index 0 is transparent
+        assert_eq!(pixel(&aseprite, 0, 0, 0), [0; 4]);
+        assert_eq!(pixel(&aseprite, 0, 1, 0), BLUE);
+    }
+
+    #[test]
+    fn hidden_layer() {
+        let data = file(
+            header(1, 1, 1, 32),
+            &[frame(
+                100,
+                &[
+                    layer("hidden", false),
+                    layer("visible", true),
+                    cel(0, 0, 0, 0, &pixels(1, 1, &RED)),
+                ],
+            )],
+        );
+        let aseprite = Aseprite::new(&data).unwrap();
+        assert!(!aseprite.layers[0].visible);
+        assert_eq!(pixel(&aseprite, 0, 0, 0), [0; 4]);
+    }
+
+    #[test]
+    fn tags_and_slices() {
+        let mut tags = Vec::new();
+        tags.extend(1u16.to_le_bytes());
+        tags.extend([0; 8]);
+        tags.extend(0u16.to_le_bytes());
+        tags.extend(1u16.to_le_bytes());
+        tags.push(2);
+        tags.extend([0; 12]);
+        tags.extend(string("walk"));
+
+        let mut slice = Vec::new();
+        slice.extend(1u32.to_le_bytes());
+        slice.extend(2u32.to_le_bytes());
+        slice.extend([0; 4]);
+        slice.extend(string("hitbox"));
+        for value in [0u32, 1, 2, 3, 4, 5, 6] {
+            slice.extend(value.to_le_bytes());
+        }
+
+        let data = file(
+            header(2, 1, 1, 32),
+            &[
+                frame(100, &[chunk(0x2018, &tags), chunk(0x2022, &slice)]),
+                frame(100, &[]),
+            ],
+        );
+        let aseprite = Aseprite::new(&data).unwrap();
+        let tag = aseprite.tag("walk").unwrap();
+        assert_eq!((tag.from, tag.to), (0, 1));
+        assert_eq!(tag.direction, AsepriteDirection::PingPong);
+        assert_eq!(aseprite.clip(tag).frames.len(), 2);
+
+        let key = aseprite.slice("hitbox").unwrap().key(1).unwrap();
+        assert_eq!(key.bounds, Rectangle::new(1.0, 2.0, 3.0, 4.0));
+        assert_eq!(key.center, None);
+        assert_eq!(key.pivot, Some(vec2(5.0, 6.0)));
+    }
+
+    #[test]
+    fn truncated() {
+        let data = file(
+            header(1, 2, 1, 32),
+            &[frame(
+                100,
+                &[
+                    layer("a", true),
+                    cel(0, 0, 0, 0, &pixels(2, 1, &[RED, BLUE].concat())),
+                ],
+            )],
+        );
+        for len in [0, 20, 127, 140, data.len() - 1] {
+            assert!(Aseprite::new(&data[..len]).is_err(), "{} bytes", len);
+        }
+    }
+
+    #[test]
+    fn oversized_palette() {
+        let mut palette = Vec::new();
+        palette.extend(1u32.to_le_bytes());
+        palette.extend(0u32.to_le_bytes());
+        palette.extend(u32::MAX.to_le_bytes());
+        palette.extend([0; 8]);
+        let data = file(
+            header(1, 1, 1, 8),
+            &[frame(100, &[chunk(0x2019, &palette)])],
+        );
+        assert!(Aseprite::new(&data).is_err());
+    }
+
+    #[test]
+    fn oversized_slice() {
+        let mut slice = Vec::new();
+        slice.extend(u32::MAX.to_le_bytes());
+        slice.extend(0u32.to_le_bytes());
+        slice.extend([0; 4]);
+        slice.extend(string("a"));
+        let data = file(header(1, 1, 1, 32), &[frame(100, &[chunk(0x2022, &slice)])]);
+        assert!(Aseprite::new(&data).is_err());
+    }
+
+    #[test]
+    fn empty_chunk() {
+        let mut frame = frame(100, &[layer("a", true)]);
+        frame[16..20].copy_from_slice(&0u32.to_le_bytes());
+        frame[12..16].copy_from_slice(&u32::MAX.to_le_bytes());
+        let data = file(header(1, 1, 1, 32), &[frame]);
+        assert!(Aseprite::new(&data).is_err());
+    }
+
+    #[test]
+    fn chunk_past_frame() {
+        let mut frame = frame(100, &[layer("a", true)]);
+        frame[16..20].copy_from_slice(&1000u32.to_le_bytes());
+        let data = file(header(1, 1, 1, 32), &[frame]);
+        assert!(Aseprite::new(&data).is_err());
+    }
+
+    #[test]
+    fn empty_frame() {
+        let mut frame = frame(100, &[]);
+        frame[0..4].copy_from_slice(&0u32.to_le_bytes());
+        let data = file(header(u16::MAX, 1, 1, 32), &[frame]);
+        assert!(Aseprite::new(&data).is_err());
+    }
+
+    #[test]
+    fn oversized_sheet() {
+        let data = file(header(4, u16::MAX, u16::MAX, 32), &[]);
+        assert!(Aseprite::new(&data).is_err());
+        let data = file(header(u16::MAX, 256, 1, 32), &[]);
+        assert!(Aseprite::new(&data).is_err());
+    }
+
+    #[test]
+    fn compressed_cel_larger_than_size() {
+        let compressed = miniz_oxide::deflate::compress_to_vec_zlib(&[0; 1024], 6);
+        let data = file(
+            header(1, 2, 1, 32),
+            &[frame(
+                100,
+                &[
+                    layer("a", true),
+                    cel(0, 0, 0, 2, &pixels(2, 1, &compressed)),
+                ],
+            )],
+        );
+        assert!(Aseprite::new(&data).is_err());
+    }
+
+    #[test]
+    fn json_hash_order() {
+        let frame = |x: u32| {
+            format!(
+                r#"{{ "frame": {{ "x": {}, "y": 0, "w": 1, "h": 1 }}, "sourceSize": {{ "w": 1, "h": 1 }}, "duration": 100 }}"#,
+                x
+            )
+        };
+        let json = format!(
+            r#"{{ "frames": {{ "b 2": {}, "a 10": {}, "c 1": {} }}, "meta": {{}} }}"#,
+            frame(0),
+            frame(1),
+            frame(2)
+        );
+        let aseprite = Aseprite::from_json(&json, Image(image::RgbaImage::new(3, 1))).unwrap();
+        assert_eq!(
+            aseprite
+                .frames
+                .iter()
+                .map(|frame| frame.source.x)
+                .collect::<Vec<_>>(),
+            [0.0, 1.0, 2.0]
+        );
+    }
+}
@@ -0,0 +1,463 @@
+use crate::{context::Context, error::ParseError, math::Rectangle, utils::HashMap};
+
+use super::{Image, Texture};
+
+/// Packs many images into one or more texture pages.
+pub struct AtlasBuilder {
+    width: u32,
+    height: u32,
+    padding: u32,
+    extrude: u32,
+    images: Vec<(String, Image)>,
+}
+
+/// Position of a packed image inside an atlas.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AtlasRegion {
+    pub page: usize,
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Sizes of the pages in an atlas and the regions packed into them.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct AtlasLayout {
+    pub pages: Vec<(u32, u32)>,
+    pub regions: HashMap<String, AtlasRegion>,
+}
+
+/// Texture pages created from an [AtlasLayout].
+#[derive(Debug, Clone)]
+pub struct Atlas {
+    pages: Vec<Texture>,
+    layout: AtlasLayout,
+}
+
+impl AtlasBuilder {
+    /// Create an atlas builder with pages of the given size.
+    /// Images larger than the page size are put on their own page.
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            width,
+            height,
+            padding: 0,
+            extrude: 0,
+            images: Vec::new(),
+        }
+    }
+
+    /// Transparent space left between packed images.
+    pub fn padding(mut self, padding: u32) -> Self {
+        self.padding = padding;
+        self
+    }
+
+    /// Number of times the edge pixels of each image are repeated around it,
+    /// which prevents neighbouring images bleeding in when drawing scaled.
+    pub fn extrude(mut self, extrude: u32) -> Self {
+        self.extrude = extrude;
+        self
+    }
+
+    pub fn add(&mut self, name: impl Into<String>, image: impl Into<Image>) {
+        self.images.push((name.into(), image.into()));
+    }
+
+    pub fn with(mut self, name: impl Into<String>, image: impl Into<Image>) -> Self {
+        self.add(name, image);
+        self
+    }
+
+    pub fn len(&self) -> usize {
+        self.images.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.images.is_empty()
+    }
+
+    /// Packs the added images, returning the page images and where each image was placed.
+    pub fn build(self) -> (Vec<Image>, AtlasLayout) {
+        let border = self.extrude * 2 + self.padding;
+
+        let mut order = (0..self.images.len()).collect::<Vec<_>>();
+        order.sort_by_key(|i| {
+            let image = &self.images[*i].1;
+            (
+                std::cmp::Reverse(image.height()),
+                std::cmp::Reverse(image.width()),
+            )
+        });
+
+        let mut shelves: Vec<Shelf> = Vec::new();
+        let mut placed = vec![None; self.images.len()];
+
+        for index in order {
+            let image = &self.images[index].1;
+            let (width, height) = (image.width() + border, image.height() + border);
+
+            let position = shelves
+                .iter_mut()
+                .enumerate()
+                .find_map(|(page, shelf)| shelf.place(width, height).map(|pos| (page, pos)));
+
+            let (page, (x, y)) = match position {
+                Some(position) => position,
+                None => {
+                    let mut shelf = Shelf::new(self.width.max(width), self.height.max(height));
+                    let position = shelf.place(width, height).unwrap_or((0, 0));
+                    shelves.push(shelf);
+                    (shelves.len() - 1, position)
+                }
+            };
+
+            placed[index] = Some(AtlasRegion {
+                page,
+                x: x + self.extrude,
+                y: y + self.extrude,
+                width: image.width(),
+                height: image.height(),
+            });
+        }
+
+        let mut pages = shelves
+            .iter()
+            .map(|shelf| image::RgbaImage::new(shelf.width, shelf.height))
+            .collect::<Vec<_>>();
+
+        let mut layout = AtlasLayout {
+            pages: shelves
+                .iter()
+                .map(|shelf| (shelf.width, shelf.height))
+                .collect(),
+            regions: HashMap::default(),
+        };
+
+        for ((name, image), region) in self.images.into_iter().zip(placed) {
+            if let Some(region) = region {
+                blit_extruded(&mut pages[region.page], &image.0, &region, self.extrude);
+                layout.regions.insert(name, region);
+            }
+        }
+
+        (pages.into_iter().map(Image).collect(), layout)
+    }
+}
+
+/// Simple shelf packer filling rows from top to bottom.
+struct Shelf {
+    width: u32,
+    height: u32,
+    x: u32,
+    y: u32,
+    row: u32,
+}
+
+impl Shelf {
+    fn new(width: u32, height: u32) -> Self {
+        Self {
+            width,
+            height,
+            x: 0,
+            y: 0,
+            row: 0,
+        }
+    }
+
+    /// Finds room for an image, only moving to a new row if the image fits there.
+    fn place(&mut self, width: u32, height: u32) -> Option<(u32, u32)> {
+        let (x, y, row) = match self.x + width > self.width {
+            true => (0, self.y + self.row, 0),
+            false => (self.x, self.y, self.row),
+        };
+        if x + width > self.width || y + height > self.height {
+            return None;
+        }
+        self.x = x + width;
+        self.y = y;
+        self.row = row.max(height);
+        Some((x, y))
+    }
+}
+
+fn blit_extruded(
+    page: &mut image::RgbaImage,
+    image: &image::RgbaImage,
+    region: &AtlasRegion,
+    extrude: u32,
+) {
+    let (width, height) = (image.width() as i64, image.height() as i64);
+    // empty images have no edge pixels to extrude
+    if width == 0 || height == 0 {
+        return;
+    }
+    let extrude = extrude as i64;
+    for y in -extrude..height + extrude {
+        for x in -extrude..width + extrude {
+            let pixel =
+                *image.get_pixel(x.clamp(0, width - 1) as u32, y.clamp(0, height - 1) as u32);
+            page.put_pixel(
+                (region.x as i64 + x) as u32,
+                (region.y as i64 + y) as u32,
+                pixel,
+            );
+        }
+    }
+}
+
+impl AtlasRegion {
+    /// The area of the atlas page to use as [DrawParams::source](super::DrawParams::source).
+    pub fn source(&self) -> Rectangle {
+        Rectangle::new(self.x as _, self.y as _, self.width as _, self.height as _)
+    }
+}
+
+impl AtlasLayout {
+    pub fn get(&self, name: &str) -> Option<&AtlasRegion> {
+        self.regions.get(name)
+    }
+
+    /// Reads a layout written by [AtlasLayout::to_string].
+    ///
+    /// The format has one entry per line:
+    /// `page <width> <height>` for each page in order and
+    /// `region <page> <x> <y> <width> <height> <name>` for each region.
+    pub fn parse(data: &str) -> Result<Self, ParseError> {
+        const FORMAT: &str = "atlas layout";
+
+        fn number<T: std::str::FromStr>(line: usize, value: Option<&str>) -> Result<T, ParseError> {
+            value.and_then(|value| value.parse().ok()).ok_or_else(|| {
+                ParseError::new(FORMAT, format!("expected number on line {}", line + 1))
+            })
+        }
+
+        let mut layout = Self::default();
+
+        for (line, text) in data.lines().enumerate() {
+            let (entry, mut rest) = field(text);
+            let mut values = std::iter::from_fn(|| {
+                let (value, remaining) = field(rest);
+                rest = remaining;
+                Some(value).filter(|value| !value.is_empty())
+            });
+            match Some(entry).filter(|entry| !entry.is_empty()) {
+                Some("page") => {
+                    let width = number(line, values.next())?;
+                    let height = number(line, values.next())?;
+                    layout.pages.push((width, height));
+                }
+                Some("region") => {
+                    let region = AtlasRegion {
+                        page: number(line, values.next())?,
+                        x: number(line, values.next())?,
+                        y: number(line, values.next())?,
+                        width: number(line, values.next())?,
+                        height: number(line, values.next())?,
+                    };
+                    if region.page >= layout.pages.len() {
+                        return Err(ParseError::new(
+                            FORMAT,
+                            format!("unknown page {} on line {}", region.page, line + 1),
+                        ));
+                    }
+                    let name = rest.trim();
+                    if name.is_empty() {
+                        return Err(ParseError::new(
+                            FORMAT,
+                            format!("missing name on line {}", line + 1),
+                        ));
+                    }
+                    layout.regions.insert(name.to_owned(), region);
+                }
+                None => (),
+                Some(other) => {
+                    return Err(ParseError::new(
+                        FORMAT,
+                        format!("unknown entry \"{}\" on line {}", other, line + 1),
+                    ))
+                }
+            }
+        }
+
+        Ok(layout)
+    }
+}
+
+/// Splits off the first whitespace separated field, returning it and the rest of the text.
+fn field(text: &str) -> (&str, &str) {
+    let text = text.trim_start();
+    text.split_once(char::is_whitespace).unwrap_or((text, ""))
+}
+
+impl std::fmt::Display for AtlasLayout {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (width, height) in &self.pages {
+            writeln!(f, "page {} {}", width, height)?;
+        }
+        let mut regions = self.regions.iter().collect::<Vec<_>>();
+        regions.sort_by_key(|(name, ..)| *name);
+        for (name, region) in regions {
+            writeln!(
+                f,
+                "region {} {} {} {} {} {}",
+                region.page, region.x, region.y, region.width, region.height, name
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl Atlas {
+    pub fn new(ctx: &mut Context, pages: &[Image], layout: AtlasLayout) -> Self {
+        Self {
+            pages: pages
+                .iter()
+                .map(|page| Texture::from_image(ctx, page))
+                .collect(),
+            layout,
+        }
+    }
+
+    pub fn from_builder(ctx: &mut Context, builder: AtlasBuilder) -> Self {
+        let (pages, layout) = builder.build();
+        Self::new(ctx, &pages, layout)
+    }
+
+    /// Returns the texture page and source rectangle of an image in the atlas.
+    pub fn get(&self, name: &str) -> Option<(&Texture, Rectangle)> {
+        self.layout
+            .get(name)
+            .and_then(|region| Some((self.pages.get(region.page)?, region.source())))
+    }
+
+    pub fn pages(&self) -> &[Texture] {
+        &self.pages
+    }
+
+    pub fn layout(&self) -> &AtlasLayout {
+        &self.layout
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn image(width: u32, height: u32, color: [u8; 4]) -> Image {
+        Image(image::RgbaImage::from_pixel(
+            width,
+            height,
+            image::Rgba(color),
+        ))
+    }
+
+    #[test]
+    fn packs_shelves() {
+        let (pages, layout) = AtlasBuilder::new(8, 8)
+            .with("a", image(4, 4, [1; 4]))
+            .with("b", image(4, 2, [2; 4]))
+            .with("c", image(4, 4, [3; 4]))
+            .with("d", image(8, 4, [4; 4]))
+            .build();
+
+        assert_eq!(layout.pages, vec![(8, 8), (8, 8)]);
+        assert_eq!(pages.len(), 2);
+
+        let region = |page, x, y, width, height| AtlasRegion {
+            page,
+            x,
+            y,
+            width,
+            height,
+        };
+        // sorted by height, then width
+        assert_eq!(layout.get("d"), Some(&region(0, 0, 0, 8, 4)));
+        assert_eq!(layout.get("a"), Some(&region(0, 0, 4, 4, 4)));
+        assert_eq!(layout.get("c"), Some(&region(0, 4, 4, 4, 4)));
+        assert_eq!(layout.get("b"), Some(&region(1, 0, 0, 4, 2)));
+
+        assert_eq!(pages[1].0.get_pixel(3, 1).0, [2; 4]);
+        assert_eq!(pages[1].0.get_pixel(4, 1).0, [0; 4]);
+    }
+
+    #[test]
+    fn oversized_images_get_their_own_page() {
+        let (pages, layout) = AtlasBuilder::new(4, 4)
+            .with("big", image(6, 5, [1; 4]))
+            .build();
+        assert_eq!(layout.pages, vec![(6, 5)]);
+        assert_eq!((pages[0].width(), pages[0].height()), (6, 5));
+    }
+
+    #[test]
+    fn padding_and_extrude() {
+        let mut corner = image(2, 2, [1, 1, 1, 255]);
+        corner.0.put_pixel(0, 0, image::Rgba([9, 9, 9, 255]));
+        let (pages, layout) = AtlasBuilder::new(16, 16)
+            .padding(1)
+            .extrude(1)
+            .with("a", corner)
+            .with("b", image(2, 2, [2, 2, 2, 255]))
+            .build();
+
+        let a = layout.get("a").unwrap();
+        let b = layout.get("b").unwrap();
+        assert_eq!((a.x, a.y), (1, 1));
+        // two pixels, two extruded edges and one padding
+        assert_eq!((b.x, b.y), (6, 1));
+
+        let page = &pages[0].0;
+        assert_eq!(page.get_pixel(0, 0).0, [9, 9, 9, 255]);
+        assert_eq!(page.get_pixel(1, 0).0, [9, 9, 9, 255]);
+        assert_eq!(page.get_pixel(3, 3).0, [1, 1, 1, 255]);
+        // padding stays transparent
+        assert_eq!(page.get_pixel(4, 1).0, [0; 4]);
+        assert_eq!(page.get_pixel(5, 1).0, [2, 2, 2, 255]);
+    }
+
+    #[test]
+    fn layout_round_trip() {
+        let (_, layout) = AtlasBuilder::new(8, 8)
+            .padding(1)
+            .with("a", image(3, 3, [1; 4]))
+            .with("with space", image(2, 5, [2; 4]))
+            .with("c", image(9, 1, [3; 4]))
+            .build();
+
+        assert_eq!(AtlasLayout::parse(&layout.to_string()).unwrap(), layout);
+    }
+
+    #[test]
+    fn parse_whitespace() {
+        let layout =
+            AtlasLayout::parse("page  16\t8\n\n  region 0  1\t2 3   4  a name \n").unwrap();
+        assert_eq!(layout.pages, vec![(16, 8)]);
+        assert_eq!(
+            layout.get("a name"),
+            Some(&AtlasRegion {
+                page: 0,
+                x: 1,
+                y: 2,
+                width: 3,
+                height: 4,
+            })
+        );
+    }
+
+    #[test]
+    fn parse_rejects() {
+        for data in [
+            "page 16",
+            "page 16 x",
+            "region 0 0 0 1 1 a",
+            "page 1 1\nregion 0 0 0 1 1",
+            "page 1 1\nregion 0 0 0 1",
+            "sprite 0",
+        ] {
+            assert!(AtlasLayout::parse(data).is_err(), "{:?}", data);
+        }
+    }
+}
@@ -0,0 +1,229 @@
+use macroquad::prelude::{get_internal_gl, DrawMode, Vertex};
+
+use crate::{
+    context::Context,
+    math::{vec2, Rectangle, Vec2},
+};
+
+use super::{BlendMode, DrawParams, Texture};
+
+/// Maximum number of sprites submitted in a single geometry call.
+/// Keeps each call under macroquad's default vertex and index limits.
+const SPRITES_PER_CALL: usize = 512;
+
+/// Identifies a sprite added to a [SpriteBatch].
+/// Identifiers of removed sprites may be reused by later sprites.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SpriteId(usize);
+
+/// A collection of sprites sharing one [Texture] that are drawn together.
+pub struct SpriteBatch {
+    texture: Texture,
+    sprites: Vec<Option<(Vec2, DrawParams)>>,
+    free: Vec<usize>,
+    vertices: Vec<Vertex>,
+    indices: Vec<u16>,
+    /// Blend mode and number of consecutive sprites using it.
+    runs: Vec<(Option<BlendMode>, usize)>,
+    dirty: bool,
+}
+
+impl SpriteBatch {
+    pub fn new(texture: Texture) -> Self {
+        Self::with_capacity(texture, 0)
+    }
+
+    pub fn with_capacity(texture: Texture, capacity: usize) -> Self {
+        Self {
+            texture,
+            sprites: Vec::with_capacity(capacity),
+            free: Vec::new(),
+            vertices: Vec::with_capacity(capacity * 4),
+            indices: Vec::new(),
+            runs: Vec::new(),
+            dirty: false,
+        }
+    }
+
+    pub fn add(&mut self, x: f32, y: f32, params: DrawParams) -> SpriteId {
+        self.dirty = true;
+        match self.free.pop() {
+            Some(index) => {
+                self.sprites[index] = Some((vec2(x, y), params));
+                SpriteId(index)
+            }
+            None => {
+                self.sprites.push(Some((vec2(x, y), params)));
+                SpriteId(self.sprites.len() - 1)
+            }
+        }
+    }
+
+    /// Replaces a sprite in the batch. Returns false if the sprite does not exist.
+    pub fn set(&mut self, id: SpriteId, x: f32, y: f32, params: DrawParams) -> bool {
+        match self.sprites.get_mut(id.0) {
+            Some(Some(sprite)) => {
+                *sprite = (vec2(x, y), params);
+                self.dirty = true;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    pub fn get(&self, id: SpriteId) -> Option<(Vec2, &DrawParams)> {
+        self.sprites
+            .get(id.0)
+            .and_then(Option::as_ref)
+            .map(|(position, params)| (*position, params))
+    }
+
+    /// Removes a sprite from the batch. Returns false if the sprite does not exist.
+    pub fn remove(&mut self, id: SpriteId) -> bool {
+        match self.sprites.get_mut(id.0) {
+            Some(sprite @ Some(..)) => {
+                *sprite = None;
+                self.free.push(id.0);
+                self.dirty = true;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.sprites.clear();
+        self.free.clear();
+        self.vertices.clear();
+        self.runs.clear();
+        self.dirty = false;
+    }
+
+    pub fn len(&self) -> usize {
+        self.sprites.len() - self.free.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn texture(&self) -> &Texture {
+        &self.texture
+    }
+
+    pub fn set_texture(&mut self, texture: Texture) {
+        self.texture = texture;
+        self.dirty = true;
+    }
+
+    /// Draws every sprite in the order they were added.
+    ///
+    /// Sprites use the blend mode in their [DrawParams],
+    /// and the batch is split wherever the blend mode changes.
+    /// The draw order of the parameters is ignored.
+    pub fn draw(&mut self, ctx: &mut Context) {
+        if self.dirty {
+            self.build();
+        }
+
+        if self.vertices.is_empty() {
+            return;
+        }
+
+        let mut start = 0;
+        for &(blend, count) in &self.runs {
+            super::apply_blend_mode(ctx, blend);
+
+            let gl = unsafe { get_internal_gl() }.quad_gl;
+            gl.texture(Some(**self.texture.data()));
+            gl.draw_mode(DrawMode::Triangles);
+
+            let run = &self.vertices[start * 4..(start + count) * 4];
+            for vertices in run.chunks(SPRITES_PER_CALL * 4) {
+                gl.geometry(vertices, &self.indices[..vertices.len() / 4 * 6]);
+            }
+            start += count;
+        }
+
+        unsafe { get_internal_gl() }.quad_gl.texture(None);
+    }
+
+    fn build(&mut self) {
+        let size = vec2(self.texture.width(), self.texture.height());
+
+        self.vertices.clear();
+        self.vertices.extend(
+            self.sprites
+                .iter()
+                .flatten()
+                .flat_map(|(position, params)| quad(size, *position, params)),
+        );
+
+        self.runs.clear();
+        for (.., params) in self.sprites.iter().flatten() {
+            match self.runs.last_mut() {
+                Some((blend, count)) if *blend == params.blend => *count += 1,
+                _ => self.runs.push((params.blend, 1)),
+            }
+        }
+
+        if self.indices.is_empty() {
+            self.indices = (0..SPRITES_PER_CALL as u16)
+                .flat_map(|i| {
+                    let i = i * 4;
+                    [i, i + 1, i + 2, i, i + 2, i + 3]
+                })
+                .collect();
+        }
+
+        self.dirty = false;
+    }
+}
+
+/// Creates the vertices of a textured quad the same way `Texture::draw` positions one.
+fn quad(texture: Vec2, position: Vec2, params: &DrawParams) -> [Vertex; 4] {
+    let source = params
+        .source
+        .unwrap_or_else(|| Rectangle::new(0.0, 0.0, texture.x, texture.y));
+
+    let mut size = params.dest_size.unwrap_or_else(|| source.size());
+    let mut position = position;
+
+    if params.flip_x {
+        position.x += size.x;
+        size.x = -size.x;
+    }
+    if params.flip_y {
+        position.y += size.y;
+        size.y = -size.y;
+    }
+
+    let pivot = params.origin.unwrap_or(position + size / 2.0);
+    let (sin, cos) = params.rotation.sin_cos();
+
+    let corners = [
+        position,
+        vec2(position.x + size.x, position.y),
+        position + size,
+        vec2(position.x, position.y + size.y),
+    ]
+    .map(|p| {
+        let p = p - pivot;
+        vec2(p.x * cos - p.y * sin, p.x * sin + p.y * cos) + pivot
+    });
+
+    let (u1, v1) = (source.x / texture.x, source.y / texture.y);
+    let (u2, v2) = (
+        (source.x + source.w) / texture.x,
+        (source.y + source.h) / texture.y,
+    );
+
+    let color = params.color;
+
+    [
+        Vertex::new(corners[0].x, corners[0].y, 0.0, u1, v1, color),
+        Vertex::new(corners[1].x, corners[1].y, 0.0, u2, v1, color),
+        Vertex::new(corners[2].x, corners[2].y, 0.0, u2, v2, color),
+        Vertex::new(corners[3].x, corners[3].y, 0.0, u1, v2, color),
+    ]
+}
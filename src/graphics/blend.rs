@@ -0,0 +1,137 @@
+use macroquad::{
+    miniquad::{BlendFactor, BlendState, BlendValue, Equation, PipelineParams},
+    prelude::{gl_use_default_material, gl_use_material, load_material, Material, MaterialParams},
+};
+
+use crate::context::Context;
+
+/// How the colors of a draw call are combined with what has already been drawn.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BlendMode {
+    /// Draws over the destination using the source's transparency.
+    #[default]
+    Alpha,
+    /// Adds the source to the destination, brightening it. Useful for lights and glows.
+    Additive,
+    /// Multiplies the destination by the source, darkening it. Useful for shadows.
+    /// Parts of the source that should leave the destination unchanged should be white.
+    Multiply,
+    /// Inverse of multiplying, brightening the destination without going past white.
+    Screen,
+    /// Alpha blending for colors already multiplied by their transparency.
+    Premultiplied,
+    /// Replaces the destination, including its transparency.
+    Replace,
+}
+
+/// Blend modes used for drawing, tracked to only switch when a draw call needs another one.
+#[derive(Debug, Default)]
+pub(crate) struct Blending {
+    /// Mode used by draw calls that do not set one.
+    default: BlendMode,
+    /// Mode currently applied.
+    current: BlendMode,
+    /// Whether a shader is in use, which blends with the mode it was created with.
+    pub shader: bool,
+    materials: Vec<(BlendMode, Material)>,
+}
+
+impl BlendMode {
+    pub(crate) fn pipeline_params(self) -> PipelineParams {
+        let state = |source, destination| Some(BlendState::new(Equation::Add, source, destination));
+        let color_blend = match self {
+            BlendMode::Alpha => state(
+                BlendFactor::Value(BlendValue::SourceAlpha),
+                BlendFactor::OneMinusValue(BlendValue::SourceAlpha),
+            ),
+            BlendMode::Additive => state(
+                BlendFactor::Value(BlendValue::SourceAlpha),
+                BlendFactor::One,
+            ),
+            BlendMode::Multiply => state(
+                BlendFactor::Value(BlendValue::DestinationColor),
+                BlendFactor::Zero,
+            ),
+            BlendMode::Screen => state(
+                BlendFactor::One,
+                BlendFactor::OneMinusValue(BlendValue::SourceColor),
+            ),
+            BlendMode::Premultiplied => state(
+                BlendFactor::One,
+                BlendFactor::OneMinusValue(BlendValue::SourceAlpha),
+            ),
+            BlendMode::Replace => None,
+        };
+        PipelineParams {
+            color_blend,
+            ..Default::default()
+        }
+    }
+}
+
+impl Blending {
+    fn material(&mut self, mode: BlendMode) -> Option<Material> {
+        if let Some((.., material)) = self.materials.iter().find(|(m, ..)| *m == mode) {
+            return Some(*material);
+        }
+        let material = load_material(
+            super::DEFAULT_VERTEX_SHADER,
+            FRAGMENT,
+            MaterialParams {
+                pipeline_params: mode.pipeline_params(),
+                ..Default::default()
+            },
+        )
+        .ok()?;
+        self.materials.push((mode, material));
+        Some(material)
+    }
+
+    /// Marks the default material as applied after it was changed elsewhere.
+    pub fn reset(&mut self) {
+        self.current = BlendMode::Alpha;
+    }
+}
+
+const FRAGMENT: &str = r#"#version 100
+varying lowp vec4 color;
+varying lowp vec2 uv;
+
+uniform sampler2D Texture;
+
+void main() {
+    gl_FragColor = color * texture2D(Texture, uv);
+}"#;
+
+/// Sets the blend mode used by shapes, text and draw calls without one in their [DrawParams](super::DrawParams).
+///
+/// Changing it flushes the [draw queue](super::flush_draw_queue), so draw calls queued before this
+/// are not sorted with ones queued after it.
+pub fn set_blend_mode(ctx: &mut Context, mode: BlendMode) {
+    if ctx.blending.default != mode {
+        super::flush_draw_queue(ctx);
+    }
+    ctx.blending.default = mode;
+}
+
+pub fn blend_mode(ctx: &Context) -> BlendMode {
+    ctx.blending.default
+}
+
+/// Applies a blend mode for the next draw call, or the default one if None.
+/// Draw calls are only split into separate batches when the mode changes.
+pub(crate) fn apply_blend_mode(ctx: &mut Context, mode: Option<BlendMode>) {
+    let blending = &mut ctx.blending;
+    let mode = mode.unwrap_or(blending.default);
+    if blending.shader || blending.current == mode {
+        return;
+    }
+    match mode {
+        BlendMode::Alpha => gl_use_default_material(),
+        mode => match blending.material(mode) {
+            Some(material) => gl_use_material(material),
+            None => return,
+        },
+    }
+    blending.current = mode;
+}
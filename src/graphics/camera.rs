@@ -0,0 +1,290 @@
+use macroquad::prelude::{rand::gen_range, Camera2D};
+
+use crate::{
+    math::{vec2, Rectangle, Vec2},
+    Context,
+};
+
+/// A view into the game world, applied on top of the scaling mode.
+///
+/// With the default camera, world coordinates are the same as screen coordinates.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Camera {
+    /// World position of the top left corner of the view.
+    pub position: Vec2,
+    /// Scale of the world, zooming in around the center of the view.
+    pub zoom: f32,
+    /// Rotation of the view around its center in radians.
+    pub rotation: f32,
+    /// Area of the screen the camera draws to. If None - draw to the whole screen.
+    pub viewport: Option<Rectangle>,
+    /// Area of the world the view is kept inside of.
+    pub bounds: Option<Rectangle>,
+    /// How quickly the camera catches up with the target it follows.
+    /// Zero or less moves to the target immediately.
+    pub follow_speed: f32,
+    target: Option<Vec2>,
+    shake: Shake,
+    enabled: bool,
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+struct Shake {
+    intensity: f32,
+    duration: f32,
+    remaining: f32,
+    offset: Vec2,
+}
+
+impl Default for Camera {
+    fn default() -> Self {
+        Self {
+            position: Vec2::ZERO,
+            zoom: 1.0,
+            rotation: 0.0,
+            viewport: None,
+            bounds: None,
+            follow_speed: 0.0,
+            target: None,
+            shake: Default::default(),
+            enabled: true,
+        }
+    }
+}
+
+impl Camera {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Keeps the given world position at the center of the view.
+    /// Should be called every update with the position of whatever is being followed.
+    pub fn follow(&mut self, target: Vec2) {
+        self.target = Some(target);
+    }
+
+    pub fn unfollow(&mut self) {
+        self.target = None;
+    }
+
+    /// Shakes the view by up to `intensity` pixels, fading out over `duration` seconds.
+    pub fn shake(&mut self, intensity: f32, duration: f32) {
+        self.shake.intensity = intensity;
+        self.shake.duration = duration;
+        self.shake.remaining = duration;
+    }
+
+    /// Moves the view so the world position is at its center.
+    pub fn center_on(&mut self, ctx: &Context, point: Vec2) {
+        self.position = point - self.view_size(ctx) / 2.0;
+    }
+
+    /// Size of the world area shown by the camera, ignoring rotation.
+    pub fn view_size(&self, ctx: &Context) -> Vec2 {
+        self.screen_area(ctx).size() / self.zoom
+    }
+
+    /// Area of the world shown by the camera, ignoring rotation.
+    pub fn visible_area(&self, ctx: &Context) -> Rectangle {
+        let size = self.view_size(ctx);
+        let position = self.center(size) - size / 2.0;
+        Rectangle::new(position.x, position.y, size.x, size.y)
+    }
+
+    /// Converts a position on the screen to a position in the world.
+    pub fn screen_to_world(&self, ctx: &Context, point: Vec2) -> Vec2 {
+        let area = self.screen_area(ctx);
+        let half = area.size() / 2.0;
+        let offset = rotate(point - area.point() - half, self.rotation) / self.zoom;
+        offset + self.center(half * 2.0 / self.zoom)
+    }
+
+    /// Converts a position in the world to a position on the screen.
+    pub fn world_to_screen(&self, ctx: &Context, point: Vec2) -> Vec2 {
+        let area = self.screen_area(ctx);
+        let half = area.size() / 2.0;
+        let offset = point - self.center(half * 2.0 / self.zoom);
+        rotate(offset * self.zoom, -self.rotation) + area.point() + half
+    }
+
+    /// The world position at the center of the view after bounds and shaking are applied.
+    fn center(&self, view: Vec2) -> Vec2 {
+        let mut position = self.position;
+        if let Some(bounds) = self.bounds {
+            position.x = clamp_axis(position.x, view.x, bounds.x, bounds.w);
+            position.y = clamp_axis(position.y, view.y, bounds.y, bounds.h);
+        }
+        position + view / 2.0 + self.shake.offset
+    }
+
+    fn screen_area(&self, ctx: &Context) -> Rectangle {
+        let screen = ctx.scaling.screen;
+        self.viewport
+            .unwrap_or_else(|| Rectangle::new(0.0, 0.0, screen.x, screen.y))
+    }
+
+    pub(crate) fn update(&mut self, view: Vec2, delta: f32) {
+        if let Some(target) = self.target {
+            let target = target - view / 2.0;
+            self.position = match self.follow_speed > 0.0 {
+                true => {
+                    let amount = 1.0 - (-self.follow_speed * delta).exp();
+                    self.position + (target - self.position) * amount
+                }
+                false => target,
+            };
+        }
+
+        let shake = &mut self.shake;
+        if shake.remaining > 0.0 {
+            shake.remaining = (shake.remaining - delta).max(0.0);
+            let strength = shake.intensity * shake.remaining / shake.duration;
+            shake.offset = vec2(
+                gen_range(-strength, strength),
+                gen_range(-strength, strength),
+            );
+        } else {
+            shake.offset = Vec2::ZERO;
+        }
+    }
+
+    /// Creates the macroquad camera drawing the view to the window,
+    /// where the whole `screen` of the scaling mode takes up `window_viewport`.
+    pub(crate) fn to_camera2d(
+        &self,
+        window: Vec2,
+        screen: Vec2,
+        window_viewport: Rectangle,
+    ) -> Camera2D {
+        let (area, zoom, rotation) = match self.enabled {
+            true => (
+                self.viewport
+                    .unwrap_or_else(|| Rectangle::new(0.0, 0.0, screen.x, screen.y)),
+                self.zoom,
+                self.rotation,
+            ),
+            false => (Rectangle::new(0.0, 0.0, screen.x, screen.y), 1.0, 0.0),
+        };
+
+        let scale = window_viewport.size() / screen;
+        let viewport = Rectangle::new(
+            window_viewport.x + area.x * scale.x,
+            window_viewport.y + area.y * scale.y,
+            area.w * scale.x,
+            area.h * scale.y,
+        );
+
+        let view = area.size() / zoom;
+        let center = match self.enabled {
+            true => self.center(view),
+            false => view / 2.0,
+        };
+
+        let mut camera = Camera2D::from_display_rect(Rectangle::new(
+            center.x - view.x / 2.0,
+            center.y - view.y / 2.0,
+            view.x,
+            view.y,
+        ));
+        camera.rotation = -rotation.to_degrees();
+        // viewports start from the bottom of the window
+        camera.viewport = Some((
+            viewport.x as _,
+            (window.y - viewport.y - viewport.h) as _,
+            viewport.w as _,
+            viewport.h as _,
+        ));
+        camera
+    }
+}
+
+fn clamp_axis(position: f32, view: f32, min: f32, size: f32) -> f32 {
+    match view > size {
+        true => min + (size - view) / 2.0,
+        false => position.clamp(min, min + size - view),
+    }
+}
+
+fn rotate(point: Vec2, angle: f32) -> Vec2 {
+    let (sin, cos) = angle.sin_cos();
+    vec2(point.x * cos - point.y * sin, point.x * sin + point.y * cos)
+}
+
+pub fn camera(ctx: &Context) -> &Camera {
+    &ctx.camera
+}
+
+pub fn camera_mut(ctx: &mut Context) -> &mut Camera {
+    &mut ctx.camera
+}
+
+/// Uses the camera for the following draw calls.
+///
+/// Flushes the [draw queue](super::flush_draw_queue), so draw calls queued before this
+/// are not sorted with ones queued after it.
+pub fn set_camera(ctx: &mut Context, camera: Camera) {
+    super::flush_draw_queue(ctx);
+    ctx.camera = camera;
+    if ctx.canvas.is_none() {
+        super::apply_scaling(ctx);
+    }
+}
+
+/// Turns the camera on or off for the following draw calls.
+/// With the camera off, everything is drawn in screen coordinates, which is useful for UI.
+///
+/// Changing it flushes the [draw queue](super::flush_draw_queue), so draw calls queued before this
+/// are not sorted with ones queued after it.
+pub fn set_camera_enabled(ctx: &mut Context, enabled: bool) {
+    if ctx.camera.enabled != enabled {
+        super::flush_draw_queue(ctx);
+        ctx.camera.enabled = enabled;
+        if ctx.canvas.is_none() {
+            super::apply_scaling(ctx);
+        }
+    }
+}
+
+pub fn screen_to_world(ctx: &Context, point: Vec2) -> Vec2 {
+    ctx.camera.screen_to_world(ctx, point)
+}
+
+pub fn world_to_screen(ctx: &Context, point: Vec2) -> Vec2 {
+    ctx.camera.world_to_screen(ctx, point)
+}
+
+/// Area of the world the next draw calls can be seen in,
+/// covering the whole view when it is rotated.
+pub(crate) fn visible_world(ctx: &Context) -> Rectangle {
+    if let Some(canvas) = &ctx.canvas {
+        return Rectangle::new(0.0, 0.0, canvas.width(), canvas.height());
+    }
+    let camera = &ctx.camera;
+    if !camera.enabled {
+        let screen = ctx.scaling.screen;
+        return Rectangle::new(0.0, 0.0, screen.x, screen.y);
+    }
+    let area = camera.visible_area(ctx);
+    match camera.rotation == 0.0 {
+        true => area,
+        false => {
+            let center = area.point() + area.size() / 2.0;
+            let radius = area.size().length() / 2.0;
+            Rectangle::new(
+                center.x - radius,
+                center.y - radius,
+                radius * 2.0,
+                radius * 2.0,
+            )
+        }
+    }
+}
+
+/// Updates following and shaking and applies the camera for drawing.
+pub(crate) fn update_camera(ctx: &mut Context, delta: f32) {
+    let view = ctx.camera.view_size(ctx);
+    ctx.camera.update(view, delta);
+    if ctx.canvas.is_none() {
+        super::apply_scaling(ctx);
+    }
+}
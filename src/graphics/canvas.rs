@@ -0,0 +1,98 @@
+use macroquad::{
+    miniquad::RenderPass,
+    prelude::{render_target, FilterMode},
+};
+use std::rc::Rc;
+
+use crate::{
+    context::Context,
+    math::{Rectangle, Vec2},
+};
+
+use super::{DrawParams, Texture};
+
+/// An off-screen render target that can be drawn to and then used as a [Texture].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Canvas(Rc<CanvasData>);
+
+#[derive(Debug, PartialEq)]
+pub struct CanvasData {
+    texture: Texture,
+    pass: RenderPass,
+}
+
+impl Canvas {
+    #[allow(unused_variables)]
+    pub fn new(ctx: &mut Context, width: u32, height: u32) -> Self {
+        let target = render_target(width, height);
+        target.texture.set_filter(FilterMode::Nearest);
+        Self(Rc::new(CanvasData {
+            texture: Texture::crate_from_raw(target.texture),
+            pass: target.render_pass,
+        }))
+    }
+
+    pub fn draw(&self, ctx: &mut Context, x: f32, y: f32, params: DrawParams) {
+        self.0.texture.draw(ctx, x, y, params)
+    }
+
+    pub fn width(&self) -> f32 {
+        self.0.texture.width()
+    }
+
+    pub fn height(&self) -> f32 {
+        self.0.texture.height()
+    }
+
+    pub fn size(&self) -> Vec2 {
+        Vec2::new(self.width(), self.height())
+    }
+
+    pub fn texture(&self) -> &Texture {
+        &self.0.texture
+    }
+}
+
+impl Drop for CanvasData {
+    fn drop(&mut self) {
+        let gl = unsafe { macroquad::prelude::get_internal_gl() };
+        self.pass.delete(gl.quad_context);
+    }
+}
+
+/// Makes all following draw calls draw to the canvas instead of the screen.
+///
+/// Flushes the [draw queue](super::flush_draw_queue), so draw calls queued before this
+/// are not sorted with ones queued after it.
+pub fn set_canvas(ctx: &mut Context, canvas: &Canvas) {
+    use macroquad::prelude::{set_camera, Camera2D};
+
+    super::flush_draw_queue(ctx);
+
+    let mut camera = Camera2D::from_display_rect(Rectangle::new(
+        0.0,
+        0.0,
+        canvas.width(),
+        canvas.height(),
+    ));
+    // render targets are stored upside down
+    camera.zoom.y = -camera.zoom.y;
+    camera.render_target = Some(macroquad::prelude::RenderTarget {
+        texture: **canvas.0.texture.data(),
+        render_pass: canvas.0.pass,
+    });
+
+    set_camera(&camera);
+    ctx.canvas = Some(canvas.clone());
+}
+
+/// Makes all following draw calls draw to the screen again.
+///
+/// Flushes the [draw queue](super::flush_draw_queue), so draw calls queued before this
+/// are not sorted with ones queued after it.
+pub fn reset_canvas(ctx: &mut Context) {
+    super::flush_draw_queue(ctx);
+    if ctx.canvas.take().is_some() {
+        super::apply_scaling(ctx);
+    }
+}
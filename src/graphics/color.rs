@@ -0,0 +1,545 @@
+use crate::{error::ParseError, utils::HashSet};
+
+use super::{Color, Image};
+
+/// Conversions and adjustments for [Color].
+pub trait ColorExt: Sized {
+    /// Parses `#rrggbb`, `#rrggbbaa`, `#rgb` or `#rgba`. The `#` is optional.
+    fn parse_hex(hex: &str) -> Option<Self>;
+
+    /// Formats the color as `#rrggbb`, or `#rrggbbaa` if it is not opaque.
+    fn to_hex(&self) -> String;
+
+    fn to_hsv(&self) -> Hsv;
+
+    fn to_hsl(&self) -> Hsl;
+
+    /// Mixes two colors, returning this one at 0 and the other at 1.
+    fn lerp(&self, other: Self, amount: f32) -> Self;
+
+    /// Multiplies the red, green and blue channels by the alpha,
+    /// for drawing with [BlendMode::Premultiplied](super::BlendMode::Premultiplied).
+    fn premultiply(&self) -> Self;
+
+    /// Multiplies the brightness of the color, keeping its hue.
+    fn brighten(&self, factor: f32) -> Self;
+
+    /// Multiplies the saturation of the color. 0 makes it gray.
+    fn saturate(&self, factor: f32) -> Self;
+}
+
+impl ColorExt for Color {
+    fn parse_hex(hex: &str) -> Option<Self> {
+        let hex = hex.strip_prefix('#').unwrap_or(hex);
+        // from_str_radix also accepts a leading sign
+        if !hex.bytes().all(|byte| byte.is_ascii_hexdigit()) {
+            return None;
+        }
+        let digit = |i: usize| {
+            hex.get(i..i + 1)
+                .and_then(|c| u8::from_str_radix(c, 16).ok())
+        };
+        let channel = |i: usize| {
+            hex.get(i..i + 2)
+                .and_then(|c| u8::from_str_radix(c, 16).ok())
+        };
+        match hex.len() {
+            3 | 4 => Some(Color::from_rgba(
+                digit(0)? * 17,
+                digit(1)? * 17,
+                digit(2)? * 17,
+                if hex.len() == 4 { digit(3)? * 17 } else { 255 },
+            )),
+            6 | 8 => Some(Color::from_rgba(
+                channel(0)?,
+                channel(2)?,
+                channel(4)?,
+                if hex.len() == 8 { channel(6)? } else { 255 },
+            )),
+            _ => None,
+        }
+    }
+
+    fn to_hex(&self) -> String {
+        let channel = |c: f32| (c.clamp(0.0, 1.0) * 255.0).round() as u8;
+        let (r, g, b) = (channel(self.r), channel(self.g), channel(self.b));
+        match channel(self.a) {
+            255 => format!("#{:02x}{:02x}{:02x}", r, g, b),
+            a => format!("#{:02x}{:02x}{:02x}{:02x}", r, g, b, a),
+        }
+    }
+
+    fn to_hsv(&self) -> Hsv {
+        (*self).into()
+    }
+
+    fn to_hsl(&self) -> Hsl {
+        (*self).into()
+    }
+
+    fn lerp(&self, other: Self, amount: f32) -> Self {
+        Color::new(
+            self.r + (other.r - self.r) * amount,
+            self.g + (other.g - self.g) * amount,
+            self.b + (other.b - self.b) * amount,
+            self.a + (other.a - self.a) * amount,
+        )
+    }
+
+    fn premultiply(&self) -> Self {
+        Color::new(self.r * self.a, self.g * self.a, self.b * self.a, self.a)
+    }
+
+    fn brighten(&self, factor: f32) -> Self {
+        let hsv = self.to_hsv();
+        Hsv {
+            value: (hsv.value * factor).clamp(0.0, 1.0),
+            ..hsv
+        }
+        .into()
+    }
+
+    fn saturate(&self, factor: f32) -> Self {
+        let hsl = self.to_hsl();
+        Hsl {
+            saturation: (hsl.saturation * factor).clamp(0.0, 1.0),
+            ..hsl
+        }
+        .into()
+    }
+}
+
+/// A color as hue, saturation and value.
+///
+/// Hue is in degrees from 0 to 360, the other channels are from 0 to 1.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Hsv {
+    pub hue: f32,
+    pub saturation: f32,
+    pub value: f32,
+    pub alpha: f32,
+}
+
+/// A color as hue, saturation and lightness.
+///
+/// Hue is in degrees from 0 to 360, the other channels are from 0 to 1.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Hsl {
+    pub hue: f32,
+    pub saturation: f32,
+    pub lightness: f32,
+    pub alpha: f32,
+}
+
+impl Hsv {
+    pub const fn new(hue: f32, saturation: f32, value: f32, alpha: f32) -> Self {
+        Self {
+            hue,
+            saturation,
+            value,
+            alpha,
+        }
+    }
+}
+
+impl Hsl {
+    pub const fn new(hue: f32, saturation: f32, lightness: f32, alpha: f32) -> Self {
+        Self {
+            hue,
+            saturation,
+            lightness,
+            alpha,
+        }
+    }
+}
+
+impl From<Color> for Hsv {
+    fn from(color: Color) -> Self {
+        let (hue, max, min) = hue(color);
+        Self {
+            hue,
+            saturation: if max > 0.0 { (max - min) / max } else { 0.0 },
+            value: max,
+            alpha: color.a,
+        }
+    }
+}
+
+impl From<Hsv> for Color {
+    fn from(hsv: Hsv) -> Self {
+        let chroma = hsv.value * hsv.saturation;
+        from_hue(hsv.hue, chroma, hsv.value - chroma, hsv.alpha)
+    }
+}
+
+impl From<Color> for Hsl {
+    fn from(color: Color) -> Self {
+        let (hue, max, min) = hue(color);
+        let lightness = (max + min) / 2.0;
+        let saturation = match lightness {
+            l if l <= 0.0 || l >= 1.0 => 0.0,
+            l => (max - min) / (1.0 - (2.0 * l - 1.0).abs()),
+        };
+        Self {
+            hue,
+            saturation,
+            lightness,
+            alpha: color.a,
+        }
+    }
+}
+
+impl From<Hsl> for Color {
+    fn from(hsl: Hsl) -> Self {
+        let chroma = (1.0 - (2.0 * hsl.lightness - 1.0).abs()) * hsl.saturation;
+        from_hue(hsl.hue, chroma, hsl.lightness - chroma / 2.0, hsl.alpha)
+    }
+}
+
+/// Hue in degrees with the largest and smallest channels of the color.
+fn hue(color: Color) -> (f32, f32, f32) {
+    let max = color.r.max(color.g).max(color.b);
+    let min = color.r.min(color.g).min(color.b);
+    let delta = max - min;
+    let hue = if delta <= 0.0 {
+        0.0
+    } else if max == color.r {
+        60.0 * ((color.g - color.b) / delta).rem_euclid(6.0)
+    } else if max == color.g {
+        60.0 * ((color.b - color.r) / delta + 2.0)
+    } else {
+        60.0 * ((color.r - color.g) / delta + 4.0)
+    };
+    (hue, max, min)
+}
+
+/// Builds a color from its hue, chroma and the amount added to every channel.
+fn from_hue(hue: f32, chroma: f32, offset: f32, alpha: f32) -> Color {
+    let hue = hue.rem_euclid(360.0) / 60.0;
+    let x = chroma * (1.0 - (hue % 2.0 - 1.0).abs());
+    let (r, g, b) = match hue as u32 {
+        0 => (chroma, x, 0.0),
+        1 => (x, chroma, 0.0),
+        2 => (0.0, chroma, x),
+        3 => (0.0, x, chroma),
+        4 => (x, 0.0, chroma),
+        _ => (chroma, 0.0, x),
+    };
+    Color::new(r + offset, g + offset, b + offset, alpha)
+}
+
+/// An ordered set of colors, such as the colors pixel art is limited to.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Palette {
+    colors: Vec<Color>,
+}
+
+impl Palette {
+    pub fn new(colors: Vec<Color>) -> Self {
+        Self { colors }
+    }
+
+    /// Parses a `.hex` palette, with one `rrggbb` color per line.
+    pub fn parse_hex(text: &str) -> Result<Self, ParseError> {
+        text.lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .map(|line| {
+                Color::parse_hex(line).ok_or_else(|| {
+                    ParseError::new("hex palette", format!("Invalid color \"{}\"", line))
+                })
+            })
+            .collect::<Result<_, _>>()
+            .map(Self::new)
+    }
+
+    /// Parses a GIMP `.gpl` palette.
+    pub fn parse_gpl(text: &str) -> Result<Self, ParseError> {
+        let error = |message: String| ParseError::new("gpl palette", message);
+
+        let mut lines = text.lines().map(str::trim);
+        if lines.next() != Some("GIMP Palette") {
+            return Err(error("Missing \"GIMP Palette\" header".to_owned()));
+        }
+
+        lines
+            .filter(|line| {
+                !line.is_empty()
+                    && !line.starts_with('#')
+                    && !line.starts_with("Name:")
+                    && !line.starts_with("Columns:")
+            })
+            .map(|line| {
+                let mut channels = line
+                    .split_whitespace()
+                    .take(3)
+                    .map(|channel| channel.parse::<u8>().ok());
+                match (channels.next(), channels.next(), channels.next()) {
+                    (Some(Some(r)), Some(Some(g)), Some(Some(b))) => {
+                        Ok(Color::from_rgba(r, g, b, 255))
+                    }
+                    _ => Err(error(format!("Invalid color \"{}\"", line))),
+                }
+            })
+            .collect::<Result<_, _>>()
+            .map(Self::new)
+    }
+
+    /// Reads the colors of an image, such as a strip of swatches,
+    /// left to right and top to bottom. Repeated colors are only added once
+    /// and fully transparent pixels are skipped.
+    pub fn from_image(image: &Image) -> Self {
+        let mut seen = HashSet::new();
+        Self::new(
+            image
+                .0
+                .pixels()
+                .filter(|pixel| pixel[3] != 0 && seen.insert(pixel.0))
+                .map(|pixel| pixel.0.into())
+                .collect(),
+        )
+    }
+
+    /// Formats the palette as a `.hex` palette.
+    pub fn to_hex(&self) -> String {
+        self.colors
+            .iter()
+            .map(|color| format!("{}\n", &color.to_hex()[1..7]))
+            .collect()
+    }
+
+    pub fn colors(&self) -> &[Color] {
+        &self.colors
+    }
+
+    pub fn colors_mut(&mut self) -> &mut Vec<Color> {
+        &mut self.colors
+    }
+
+    pub fn get(&self, index: usize) -> Option<Color> {
+        self.colors.get(index).copied()
+    }
+
+    pub fn len(&self) -> usize {
+        self.colors.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.colors.is_empty()
+    }
+
+    /// Index of the palette color closest to the color, ignoring alpha.
+    pub fn nearest_index(&self, color: Color) -> Option<usize> {
+        let distance = |other: &Color| {
+            (other.r - color.r).powi(2) + (other.g - color.g).powi(2) + (other.b - color.b).powi(2)
+        };
+        self.colors
+            .iter()
+            .enumerate()
+            .min_by(|(_, a), (_, b)| distance(a).total_cmp(&distance(b)))
+            .map(|(index, _)| index)
+    }
+
+    /// The palette color closest to the color, keeping its alpha.
+    pub fn nearest(&self, color: Color) -> Option<Color> {
+        self.nearest_index(color).map(|index| Color {
+            a: color.a,
+            ..self.colors[index]
+        })
+    }
+}
+
+impl From<Vec<Color>> for Palette {
+    fn from(colors: Vec<Color>) -> Self {
+        Self::new(colors)
+    }
+}
+
+/// Serializes a [Color] as a hex string, for use with `#[serde(with = "hex_color")]`.
+///
+/// Colors can also be deserialized from arrays of three or four channels from 0 to 1.
+#[cfg(feature = "serde")]
+pub mod hex_color {
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    use super::{Color, ColorExt};
+
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Repr {
+        Hex(String),
+        Rgb([f32; 3]),
+        Rgba([f32; 4]),
+    }
+
+    pub fn serialize<S: Serializer>(color: &Color, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&color.to_hex())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Color, D::Error> {
+        match Repr::deserialize(deserializer)? {
+            Repr::Hex(hex) => Color::parse_hex(&hex)
+                .ok_or_else(|| serde::de::Error::custom(format!("invalid hex color \"{}\"", hex))),
+            Repr::Rgb([r, g, b]) => Ok(Color::new(r, g, b, 1.0)),
+            Repr::Rgba([r, g, b, a]) => Ok(Color::new(r, g, b, a)),
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for Palette {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.colors.iter().map(ColorExt::to_hex))
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Palette {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(serde::Deserialize)]
+        struct HexColor(#[serde(with = "hex_color")] Color);
+
+        Vec::<HexColor>::deserialize(deserializer)
+            .map(|colors| Self::new(colors.into_iter().map(|color| color.0).collect()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn parse_hex() {
+        assert_eq!(
+            Color::parse_hex("#f80"),
+            Some(Color::from_rgba(255, 136, 0, 255))
+        );
+        assert_eq!(
+            Color::parse_hex("#f808"),
+            Some(Color::from_rgba(255, 136, 0, 136))
+        );
+        assert_eq!(
+            Color::parse_hex("#ff8800"),
+            Some(Color::from_rgba(255, 136, 0, 255))
+        );
+        assert_eq!(
+            Color::parse_hex("#FF880080"),
+            Some(Color::from_rgba(255, 136, 0, 128))
+        );
+        assert_eq!(
+            Color::parse_hex("ff8800"),
+            Some(Color::from_rgba(255, 136, 0, 255))
+        );
+    }
+
+    #[test]
+    fn parse_hex_rejects() {
+        for hex in [
+            "",
+            "#",
+            "#f",
+            "#ff",
+            "#fffff",
+            "#fffffff",
+            "#fffffffff",
+            "#ggg",
+            "#+f+f+f",
+            "#-1-1-1",
+            "#ff 800",
+            "##fff",
+            "#ffé",
+        ] {
+            assert_eq!(Color::parse_hex(hex), None, "{}", hex);
+        }
+    }
+
+    #[test]
+    fn to_hex() {
+        assert_eq!(Color::from_rgba(255, 136, 0, 255).to_hex(), "#ff8800");
+        assert_eq!(Color::from_rgba(255, 136, 0, 128).to_hex(), "#ff880080");
+        let color = Color::from_rgba(18, 52, 86, 120);
+        assert_eq!(Color::parse_hex(&color.to_hex()), Some(color));
+    }
+
+    fn assert_close(a: Color, b: Color) {
+        let channels = |c: Color| [c.r, c.g, c.b, c.a];
+        assert!(
+            channels(a)
+                .iter()
+                .zip(channels(b))
+                .all(|(a, b)| (a - b).abs() < 1e-5),
+            "{:?} != {:?}",
+            a,
+            b
+        );
+    }
+
+    #[test]
+    fn hsv_round_trip() {
+        for color in [
+            Color::new(0.0, 0.0, 0.0, 1.0),
+            Color::new(0.5, 0.5, 0.5, 0.5),
+            Color::new(1.0, 1.0, 1.0, 1.0),
+            Color::new(0.2, 0.6, 0.9, 1.0),
+            Color::new(0.9, 0.1, 0.4, 0.3),
+        ] {
+            assert_close(Color::from(color.to_hsv()), color);
+            assert_close(Color::from(color.to_hsl()), color);
+        }
+
+        let gray = Color::new(0.5, 0.5, 0.5, 1.0);
+        assert_eq!(gray.to_hsv(), Hsv::new(0.0, 0.0, 0.5, 1.0));
+        assert_eq!(gray.to_hsl(), Hsl::new(0.0, 0.0, 0.5, 1.0));
+    }
+
+    #[test]
+    fn pure_hues() {
+        for (hue, color) in [
+            (0.0, Color::new(1.0, 0.0, 0.0, 1.0)),
+            (60.0, Color::new(1.0, 1.0, 0.0, 1.0)),
+            (120.0, Color::new(0.0, 1.0, 0.0, 1.0)),
+            (180.0, Color::new(0.0, 1.0, 1.0, 1.0)),
+            (240.0, Color::new(0.0, 0.0, 1.0, 1.0)),
+            (300.0, Color::new(1.0, 0.0, 1.0, 1.0)),
+        ] {
+            assert_eq!(color.to_hsv(), Hsv::new(hue, 1.0, 1.0, 1.0));
+            assert_eq!(color.to_hsl(), Hsl::new(hue, 1.0, 0.5, 1.0));
+            assert_close(Color::from(Hsv::new(hue, 1.0, 1.0, 1.0)), color);
+            assert_close(Color::from(Hsl::new(hue, 1.0, 0.5, 1.0)), color);
+        }
+    }
+
+    #[test]
+    fn hue_wraps() {
+        let red = Color::new(1.0, 0.0, 0.0, 1.0);
+        assert_close(Color::from(Hsv::new(360.0, 1.0, 1.0, 1.0)), red);
+        assert_close(Color::from(Hsl::new(720.0, 1.0, 0.5, 1.0)), red);
+        assert_close(
+            Color::from(Hsv::new(-120.0, 1.0, 1.0, 1.0)),
+            Color::new(0.0, 0.0, 1.0, 1.0),
+        );
+        // just below red, going around from magenta
+        let hue = Color::new(1.0, 0.0, 0.01, 1.0).to_hsv().hue;
+        assert!(hue > 359.0 && hue < 360.0, "{}", hue);
+    }
+
+    #[test]
+    fn palette_from_image() {
+        let mut image = Image::from_color(3, 2, Color::from_rgba(255, 0, 0, 255));
+        image.set_pixel(1, 0, Color::from_rgba(0, 0, 0, 0));
+        image.set_pixel(2, 0, Color::from_rgba(0, 255, 0, 255));
+        image.set_pixel(0, 1, Color::from_rgba(0, 0, 255, 0));
+        image.set_pixel(2, 1, Color::from_rgba(0, 0, 255, 128));
+        assert_eq!(
+            Palette::from_image(&image).colors(),
+            [
+                Color::from_rgba(255, 0, 0, 255),
+                Color::from_rgba(0, 255, 0, 255),
+                Color::from_rgba(0, 0, 255, 128),
+            ]
+        );
+    }
+}
@@ -1,11 +1,51 @@
-use image::GenericImageView;
+use image::{
+    error::{ImageFormatHint, UnsupportedError, UnsupportedErrorKind},
+    imageops, GenericImageView, Rgba,
+};
+
+use std::path::Path;
+
+use crate::error::{EngineError, FileError};
+
+use super::Color;
 
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub struct Image(pub(crate) image::RgbaImage);
 
 impl Image {
-    pub fn new(data: &[u8]) -> Result<Self, image::ImageError> {
-        Ok(Self(image::load_from_memory(data)?.to_rgba8()))
+    /// Decodes an image, detecting its format.
+    /// Only the first frame of animated images is read.
+    pub fn new(data: &[u8]) -> Result<Self, EngineError> {
+        decode(data).map(Self)
+    }
+
+    /// Decodes every frame of a GIF along with how long each is shown for, in seconds.
+    /// Frames with a delay of 10 milliseconds or less are shown for 100 milliseconds.
+    #[cfg(feature = "gif")]
+    pub fn gif_frames(data: &[u8]) -> Result<Vec<(Self, f32)>, EngineError> {
+        use image::AnimationDecoder;
+
+        let decoder = image::codecs::gif::GifDecoder::new(data)?;
+        Ok(decoder
+            .into_frames()
+            .collect_frames()?
+            .into_iter()
+            .map(|frame| {
+                let (numerator, denominator) = frame.delay().numer_denom_ms();
+                let duration = numerator as f32 / denominator.max(1) as f32 / 1000.0;
+                // browsers show frames with little or no delay for a tenth of a second
+                let duration = match duration <= 0.01 {
+                    true => 0.1,
+                    false => duration,
+                };
+                (Self(frame.into_buffer()), duration)
+            })
+            .collect())
+    }
+
+    /// Creates an image filled with one color.
+    pub fn from_color(width: u32, height: u32, color: Color) -> Self {
+        Self(image::RgbaImage::from_pixel(width, height, rgba(color)))
     }
 
     pub fn width(&self) -> u32 {
@@ -16,11 +56,325 @@ impl Image {
         self.0.height()
     }
 
+    pub fn get_pixel(&self, x: u32, y: u32) -> Option<Color> {
+        match x < self.width() && y < self.height() {
+            true => Some(color(*self.0.get_pixel(x, y))),
+            false => None,
+        }
+    }
+
+    /// Sets the color of a pixel, doing nothing if it is outside of the image.
+    pub fn set_pixel(&mut self, x: u32, y: u32, color: Color) {
+        if x < self.width() && y < self.height() {
+            self.0.put_pixel(x, y, rgba(color));
+        }
+    }
+
+    pub fn fill(&mut self, color: Color) {
+        let color = rgba(color);
+        self.0.pixels_mut().for_each(|pixel| *pixel = color);
+    }
+
+    /// Draws another image on top of this one, blending with its transparency.
+    /// Parts outside of this image are skipped.
+    pub fn blit(&mut self, image: &Image, x: i32, y: i32) {
+        blit(&mut self.0, &image.0, x, y)
+    }
+
+    /// Draws part of another image on top of this one, blending with its transparency.
+    pub fn blit_region(&mut self, region: &SubImage, x: i32, y: i32) {
+        blit(&mut self.0, &region.0, x, y)
+    }
+
+    pub fn flip_horizontal(&mut self) {
+        imageops::flip_horizontal_in_place(&mut self.0);
+    }
+
+    pub fn flip_vertical(&mut self) {
+        imageops::flip_vertical_in_place(&mut self.0);
+    }
+
+    /// Rotates the image a quarter turn clockwise.
+    pub fn rotate_clockwise(&mut self) {
+        self.0 = imageops::rotate90(&self.0);
+    }
+
+    /// Rotates the image a quarter turn counter clockwise.
+    pub fn rotate_counter_clockwise(&mut self) {
+        self.0 = imageops::rotate270(&self.0);
+    }
+
+    pub fn rotate_half(&mut self) {
+        imageops::rotate180_in_place(&mut self.0);
+    }
+
+    /// Cuts the image down to an area of it. The area is clamped to the image.
+    pub fn crop(&mut self, x: u32, y: u32, width: u32, height: u32) {
+        self.0 = imageops::crop_imm(&self.0, x, y, width, height).to_image();
+    }
+
+    pub fn resize(&mut self, width: u32, height: u32, filter: ResizeFilter) {
+        let filter = match filter {
+            ResizeFilter::Nearest => imageops::FilterType::Nearest,
+            ResizeFilter::Linear => imageops::FilterType::Triangle,
+        };
+        self.0 = imageops::resize(&self.0, width, height, filter);
+    }
+
+    /// Changes the color of every pixel.
+    pub fn recolor(&mut self, mut f: impl FnMut(Color) -> Color) {
+        for pixel in self.0.pixels_mut() {
+            *pixel = rgba(f(color(*pixel)));
+        }
+    }
+
+    /// Replaces colors exactly matching the first color of a pair with the second.
+    pub fn swap_colors(&mut self, swaps: &[(Color, Color)]) {
+        let swaps = swaps
+            .iter()
+            .map(|(from, to)| (rgba(*from), rgba(*to)))
+            .collect::<Vec<_>>();
+        for pixel in self.0.pixels_mut() {
+            if let Some((.., to)) = swaps.iter().find(|(from, ..)| from == pixel) {
+                *pixel = *to;
+            }
+        }
+    }
+
+    /// Swaps colors using two palette images as a lookup table,
+    /// replacing each color of `from` with the pixel at the same position in `to`.
+    pub fn swap_palette(&mut self, from: &Image, to: &Image) {
+        let swaps = from
+            .0
+            .enumerate_pixels()
+            .filter_map(|(x, y, from)| to.get_pixel(x, y).map(|to| (color(*from), to)))
+            .collect::<Vec<_>>();
+        self.swap_colors(&swaps);
+    }
+
+    /// Encodes the image as a file of the given format.
+    /// Only PNG and TGA can be encoded.
+    pub fn encode(&self, format: ImageFormat) -> Result<Vec<u8>, image::ImageError> {
+        let mut bytes = Vec::new();
+        let (data, width, height) = (self.0.as_raw(), self.width(), self.height());
+        match format {
+            ImageFormat::Png => image::codecs::png::PngEncoder::new(&mut bytes).encode(
+                data,
+                width,
+                height,
+                image::ColorType::Rgba8,
+            ),
+            ImageFormat::Tga => image::codecs::tga::TgaEncoder::new(&mut bytes).encode(
+                data,
+                width,
+                height,
+                image::ColorType::Rgba8,
+            ),
+            format => Err(unsupported(ImageFormatHint::Name(format.to_string()))),
+        }?;
+        Ok(bytes)
+    }
+
+    /// Writes the image to a file, choosing the format from the file's extension.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), EngineError> {
+        let path = path.as_ref();
+        let format = ImageFormat::from_path(path).ok_or_else(|| {
+            unsupported(ImageFormatHint::PathExtension(
+                path.extension().unwrap_or_default().into(),
+            ))
+        })?;
+        let bytes = self.encode(format)?;
+        std::fs::write(path, bytes).map_err(|err| EngineError::File(FileError::Io(err)))
+    }
+
     pub fn region(&self, x: u32, y: u32, w: u32, h: u32) -> SubImage {
         SubImage(self.0.view(x, y, w, h))
     }
 }
 
+/// Image file formats.
+///
+/// PNG and TGA are always supported. Other formats are read
+/// when the engine feature of the same name is enabled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageFormat {
+    Png,
+    Tga,
+    Bmp,
+    Gif,
+    Jpeg,
+    Qoi,
+    Webp,
+}
+
+impl ImageFormat {
+    /// The format of a file with the path's extension.
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Option<Self> {
+        let extension = path.as_ref().extension()?.to_str()?.to_ascii_lowercase();
+        match extension.as_str() {
+            "png" => Some(Self::Png),
+            "tga" => Some(Self::Tga),
+            "bmp" => Some(Self::Bmp),
+            "gif" => Some(Self::Gif),
+            "jpg" | "jpeg" => Some(Self::Jpeg),
+            "qoi" => Some(Self::Qoi),
+            "webp" => Some(Self::Webp),
+            _ => None,
+        }
+    }
+
+    /// Detects the format of image data from its first bytes.
+    /// TGA files have no signature, so data not matching another format
+    /// is taken to be TGA if it starts with a valid TGA header.
+    pub fn detect(data: &[u8]) -> Option<Self> {
+        if data.starts_with(b"\x89PNG\r\n\x1a\n") {
+            Some(Self::Png)
+        } else if data.starts_with(b"BM") {
+            Some(Self::Bmp)
+        } else if data.starts_with(b"GIF87a") || data.starts_with(b"GIF89a") {
+            Some(Self::Gif)
+        } else if data.starts_with(&[0xff, 0xd8, 0xff]) {
+            Some(Self::Jpeg)
+        } else if data.starts_with(b"qoif") {
+            Some(Self::Qoi)
+        } else if data.starts_with(b"RIFF") && data.get(8..12) == Some(b"WEBP") {
+            Some(Self::Webp)
+        } else if is_tga(data) {
+            Some(Self::Tga)
+        } else {
+            None
+        }
+    }
+
+    /// Whether images of this format can be read with the enabled features.
+    pub fn enabled(&self) -> bool {
+        match self {
+            Self::Png | Self::Tga => true,
+            Self::Bmp => cfg!(feature = "bmp"),
+            Self::Gif => cfg!(feature = "gif"),
+            Self::Jpeg => cfg!(feature = "jpeg"),
+            Self::Qoi => cfg!(feature = "qoi"),
+            Self::Webp => cfg!(feature = "webp"),
+        }
+    }
+
+    /// Name of the engine feature that enables reading this format.
+    pub fn feature(&self) -> Option<&'static str> {
+        match self {
+            Self::Png | Self::Tga => None,
+            Self::Bmp => Some("bmp"),
+            Self::Gif => Some("gif"),
+            Self::Jpeg => Some("jpeg"),
+            Self::Qoi => Some("qoi"),
+            Self::Webp => Some("webp"),
+        }
+    }
+}
+
+impl std::fmt::Display for ImageFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Png => "PNG",
+            Self::Tga => "TGA",
+            Self::Bmp => "BMP",
+            Self::Gif => "GIF",
+            Self::Jpeg => "JPEG",
+            Self::Qoi => "QOI",
+            Self::Webp => "WebP",
+        })
+    }
+}
+
+/// Checks the fields of a TGA header that have a fixed set of values.
+fn is_tga(data: &[u8]) -> bool {
+    let header = match data.get(..18) {
+        Some(header) => header,
+        None => return false,
+    };
+    let (color_map, kind, depth, descriptor) = (header[1], header[2], header[16], header[17]);
+    let size = |i: usize| u16::from_le_bytes([header[i], header[i + 1]]);
+    let color_map_valid = match color_map {
+        0 => true,
+        1 => matches!(header[7], 15 | 16 | 24 | 32),
+        _ => false,
+    };
+    let kind_valid = match kind {
+        // color mapped
+        1 | 9 => color_map == 1,
+        // true color and grayscale
+        2 | 3 | 10 | 11 => true,
+        _ => false,
+    };
+    color_map_valid
+        && kind_valid
+        && size(12) > 0
+        && size(14) > 0
+        && matches!(depth, 8 | 15 | 16 | 24 | 32)
+        // interleaving is not used by any supported file
+        && descriptor & 0xc0 == 0
+}
+
+/// Decodes an image, detecting its format.
+pub(crate) fn decode(data: &[u8]) -> Result<image::RgbaImage, EngineError> {
+    let format = ImageFormat::detect(data).ok_or_else(|| unsupported(ImageFormatHint::Unknown))?;
+    if !format.enabled() {
+        return Err(EngineError::UnsupportedFormat(format));
+    }
+    let format = match format {
+        ImageFormat::Png => image::ImageFormat::Png,
+        ImageFormat::Tga => image::ImageFormat::Tga,
+        ImageFormat::Bmp => image::ImageFormat::Bmp,
+        ImageFormat::Gif => image::ImageFormat::Gif,
+        ImageFormat::Jpeg => image::ImageFormat::Jpeg,
+        ImageFormat::Webp => image::ImageFormat::WebP,
+        ImageFormat::Qoi => return decode_qoi(data),
+    };
+    Ok(image::load_from_memory_with_format(data, format)?.to_rgba8())
+}
+
+#[cfg(feature = "qoi")]
+fn decode_qoi(data: &[u8]) -> Result<image::RgbaImage, EngineError> {
+    let parse_error = |err: qoi::Error| {
+        EngineError::Parse(crate::error::ParseError::new("qoi image", err.to_string()))
+    };
+    let mut decoder = qoi::Decoder::new(data)
+        .map_err(parse_error)?
+        .with_channels(qoi::Channels::Rgba);
+    let header = *decoder.header();
+    let pixels = decoder.decode_to_vec().map_err(parse_error)?;
+    image::RgbaImage::from_raw(header.width, header.height, pixels)
+        .ok_or_else(|| parse_error(qoi::Error::UnexpectedBufferEnd))
+}
+
+#[cfg(not(feature = "qoi"))]
+fn decode_qoi(_: &[u8]) -> Result<image::RgbaImage, EngineError> {
+    Err(EngineError::UnsupportedFormat(ImageFormat::Qoi))
+}
+
+/// Error for pixel data that does not match the size of its image.
+pub(crate) fn dimension_mismatch() -> EngineError {
+    EngineError::Image(image::ImageError::Parameter(
+        image::error::ParameterError::from_kind(
+            image::error::ParameterErrorKind::DimensionMismatch,
+        ),
+    ))
+}
+
+fn unsupported(hint: ImageFormatHint) -> image::ImageError {
+    image::ImageError::Unsupported(UnsupportedError::from_format_and_kind(
+        hint.clone(),
+        UnsupportedErrorKind::Format(hint),
+    ))
+}
+
+/// How pixels are sampled when resizing an image.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResizeFilter {
+    /// Keeps hard edges, best for pixel art.
+    Nearest,
+    Linear,
+}
+
 pub struct SubImage<'i>(
     image::SubImage<&'i <image::RgbaImage as image::GenericImageView>::InnerImageView>,
 );
@@ -40,3 +394,255 @@ impl From<SubImage<'_>> for Image {
         Image(image.0.to_image())
     }
 }
+
+fn rgba(color: Color) -> Rgba<u8> {
+    Rgba(color.into())
+}
+
+fn color(pixel: Rgba<u8>) -> Color {
+    pixel.0.into()
+}
+
+fn blit<I: GenericImageView<Pixel = Rgba<u8>>>(
+    destination: &mut image::RgbaImage,
+    source: &I,
+    x: i32,
+    y: i32,
+) {
+    let (width, height) = (destination.width() as i64, destination.height() as i64);
+    for (source_x, source_y, pixel) in source.pixels() {
+        let (dx, dy) = (x as i64 + source_x as i64, y as i64 + source_y as i64);
+        if dx < 0 || dy < 0 || dx >= width || dy >= height {
+            continue;
+        }
+        let under = destination.get_pixel_mut(dx as u32, dy as u32);
+        *under = blend(*under, pixel);
+    }
+}
+
+/// Draws a pixel over another, as with alpha blending.
+pub(crate) fn blend(under: Rgba<u8>, over: Rgba<u8>) -> Rgba<u8> {
+    let alpha = over[3] as f32 / 255.0;
+    let under_alpha = under[3] as f32 / 255.0 * (1.0 - alpha);
+    let out = alpha + under_alpha;
+    if out <= 0.0 {
+        return Rgba([0; 4]);
+    }
+    let channel =
+        |i: usize| ((over[i] as f32 * alpha + under[i] as f32 * under_alpha) / out).round() as u8;
+    Rgba([
+        channel(0),
+        channel(1),
+        channel(2),
+        (out * 255.0).round() as u8,
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    const RED: Rgba<u8> = Rgba([255, 0, 0, 255]);
+    const BLUE: Rgba<u8> = Rgba([0, 0, 255, 255]);
+    const CLEAR: Rgba<u8> = Rgba([0, 0, 0, 0]);
+
+    /// An image with a different color in each pixel.
+    fn numbered(width: u32, height: u32) -> Image {
+        Image(image::RgbaImage::from_fn(width, height, |x, y| {
+            Rgba([x as u8, y as u8, 0, 255])
+        }))
+    }
+
+    #[test]
+    fn blit_clips() {
+        let source = numbered(3, 3);
+
+        let mut image = Image(image::RgbaImage::from_pixel(4, 4, CLEAR));
+        image.blit(&source, -2, -1);
+        assert_eq!(*image.0.get_pixel(0, 0), Rgba([2, 1, 0, 255]));
+        assert_eq!(*image.0.get_pixel(0, 1), Rgba([2, 2, 0, 255]));
+        assert_eq!(*image.0.get_pixel(1, 0), CLEAR);
+        assert_eq!(*image.0.get_pixel(0, 2), CLEAR);
+
+        let mut image = Image(image::RgbaImage::from_pixel(4, 4, CLEAR));
+        image.blit(&source, 3, 3);
+        assert_eq!(*image.0.get_pixel(3, 3), Rgba([0, 0, 0, 255]));
+        assert_eq!(image.0.pixels().filter(|pixel| **pixel != CLEAR).count(), 1);
+
+        let mut image = Image(image::RgbaImage::from_pixel(2, 2, CLEAR));
+        image.blit(&source, i32::MIN, i32::MAX);
+        image.blit(&source, -3, 0);
+        assert!(image.0.pixels().all(|pixel| *pixel == CLEAR));
+    }
+
+    #[test]
+    fn blit_blends() {
+        let mut image = Image(image::RgbaImage::from_pixel(1, 1, RED));
+        image.blit(&Image(image::RgbaImage::from_pixel(1, 1, CLEAR)), 0, 0);
+        assert_eq!(*image.0.get_pixel(0, 0), RED);
+        image.blit(
+            &Image(image::RgbaImage::from_pixel(1, 1, Rgba([0, 0, 255, 128]))),
+            0,
+            0,
+        );
+        assert_eq!(*image.0.get_pixel(0, 0), Rgba([127, 0, 128, 255]));
+    }
+
+    #[test]
+    fn blit_region() {
+        let source = numbered(4, 4);
+        let mut image = Image(image::RgbaImage::from_pixel(2, 2, CLEAR));
+        image.blit_region(&source.region(1, 2, 2, 2), 0, 0);
+        assert_eq!(*image.0.get_pixel(0, 0), Rgba([1, 2, 0, 255]));
+        assert_eq!(*image.0.get_pixel(1, 1), Rgba([2, 3, 0, 255]));
+    }
+
+    #[test]
+    fn swap_palette() {
+        let mut image = Image(
+            image::RgbaImage::from_vec(3, 1, [RED, BLUE, CLEAR].iter().flat_map(|p| p.0).collect())
+                .unwrap(),
+        );
+        let from = Image(
+            image::RgbaImage::from_vec(2, 1, [RED, BLUE].iter().flat_map(|p| p.0).collect())
+                .unwrap(),
+        );
+        let to = Image(
+            image::RgbaImage::from_vec(2, 1, [BLUE, RED].iter().flat_map(|p| p.0).collect())
+                .unwrap(),
+        );
+        image.swap_palette(&from, &to);
+        assert_eq!(
+            image.0.pixels().copied().collect::<Vec<_>>(),
+            [BLUE, RED, CLEAR]
+        );
+
+        // colors outside of the second palette are left alone
+        let mut image = Image(image::RgbaImage::from_pixel(1, 1, BLUE));
+        image.swap_palette(&from, &Image(image::RgbaImage::from_pixel(1, 1, CLEAR)));
+        assert_eq!(*image.0.get_pixel(0, 0), BLUE);
+    }
+
+    #[test]
+    fn crop_clamps() {
+        let mut image = numbered(4, 3);
+        image.crop(1, 1, 2, 1);
+        assert_eq!((image.width(), image.height()), (2, 1));
+        assert_eq!(*image.0.get_pixel(0, 0), Rgba([1, 1, 0, 255]));
+
+        let mut image = numbered(4, 3);
+        image.crop(2, 1, 10, 10);
+        assert_eq!((image.width(), image.height()), (2, 2));
+        assert_eq!(*image.0.get_pixel(1, 1), Rgba([3, 2, 0, 255]));
+
+        let mut image = numbered(4, 3);
+        image.crop(10, 10, 2, 2);
+        assert_eq!((image.width(), image.height()), (0, 0));
+    }
+
+    #[test]
+    fn pixels() {
+        let mut image = numbered(2, 2);
+        assert_eq!(image.get_pixel(2, 0), None);
+        image.set_pixel(5, 5, Color::from_rgba(1, 2, 3, 4));
+        assert_eq!(image, numbered(2, 2));
+        image.set_pixel(1, 0, Color::from_rgba(1, 2, 3, 4));
+        assert_eq!(image.get_pixel(1, 0), Some(Color::from_rgba(1, 2, 3, 4)));
+    }
+
+    #[test]
+    fn rotate() {
+        let mut image = numbered(3, 2);
+        image.rotate_clockwise();
+        assert_eq!((image.width(), image.height()), (2, 3));
+        assert_eq!(*image.0.get_pixel(1, 0), Rgba([0, 0, 0, 255]));
+        image.rotate_counter_clockwise();
+        assert_eq!(image, numbered(3, 2));
+    }
+
+    #[test]
+    fn encode_round_trip() {
+        let mut image = numbered(5, 3);
+        image.set_pixel(4, 2, Color::from_rgba(10, 20, 30, 40));
+        for format in [ImageFormat::Png, ImageFormat::Tga] {
+            let bytes = image.encode(format).unwrap();
+            assert_eq!(ImageFormat::detect(&bytes), Some(format));
+            assert_eq!(Image::new(&bytes).unwrap(), image, "{}", format);
+        }
+        assert!(image.encode(ImageFormat::Jpeg).is_err());
+    }
+
+    #[test]
+    fn detect() {
+        let tga = |kind: u8, depth: u8| {
+            let mut header = [0u8; 18];
+            header[2] = kind;
+            header[12..14].copy_from_slice(&4u16.to_le_bytes());
+            header[14..16].copy_from_slice(&2u16.to_le_bytes());
+            header[16] = depth;
+            header
+        };
+        let detect = ImageFormat::detect;
+
+        assert_eq!(detect(b"\x89PNG\r\n\x1a\n...."), Some(ImageFormat::Png));
+        assert_eq!(detect(b"BM...."), Some(ImageFormat::Bmp));
+        assert_eq!(detect(b"GIF89a...."), Some(ImageFormat::Gif));
+        assert_eq!(detect(&[0xff, 0xd8, 0xff, 0xe0]), Some(ImageFormat::Jpeg));
+        assert_eq!(detect(b"qoif...."), Some(ImageFormat::Qoi));
+        assert_eq!(detect(b"RIFF\0\0\0\0WEBPVP8 "), Some(ImageFormat::Webp));
+        assert_eq!(detect(&tga(2, 32)), Some(ImageFormat::Tga));
+        assert_eq!(detect(&tga(11, 8)), Some(ImageFormat::Tga));
+
+        let mut mapped = tga(1, 8);
+        assert_eq!(detect(&mapped), None);
+        mapped[1] = 1;
+        mapped[7] = 24;
+        assert_eq!(detect(&mapped), Some(ImageFormat::Tga));
+
+        for data in [
+            &b""[..],
+            b"garbage that is long enough to be a header",
+            // tiff, ico and avif
+            b"II*\0\x08\0\0\0\0\0\0\0\0\0\0\0\0\0",
+            b"\0\0\x01\0\x01\0\x10\x10\0\0\x01\0\x20\0\0\0\0\0",
+            b"\0\0\0\x1cftypavif\0\0\0\0avifmif1",
+            &tga(2, 12),
+            &tga(0, 32),
+            &tga(2, 32)[..17],
+        ] {
+            assert_eq!(detect(data), None, "{:?}", data);
+        }
+    }
+
+    #[test]
+    fn unknown_format() {
+        match Image::new(b"not an image, but long enough to be a header") {
+            Err(EngineError::Image(image::ImageError::Unsupported(..))) => (),
+            other => panic!("expected unsupported format, got {:?}", other),
+        }
+    }
+
+    #[cfg(feature = "gif")]
+    #[test]
+    fn gif_frames() {
+        let mut bytes = Vec::new();
+        {
+            let mut encoder = image::codecs::gif::GifEncoder::new(&mut bytes);
+            for delay in [0, 50] {
+                let frame = image::Frame::from_parts(
+                    numbered(2, 2).0,
+                    0,
+                    0,
+                    image::Delay::from_numer_denom_ms(delay, 1),
+                );
+                encoder.encode_frame(frame).unwrap();
+            }
+        }
+        let frames = Image::gif_frames(&bytes).unwrap();
+        assert_eq!(
+            frames.iter().map(|(.., delay)| *delay).collect::<Vec<_>>(),
+            [0.1, 0.05]
+        );
+    }
+}
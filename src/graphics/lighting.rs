@@ -0,0 +1,411 @@
+use macroquad::prelude::Vertex;
+use std::f32::consts::TAU;
+
+use crate::{
+    context::Context,
+    math::{vec2, Rectangle, Vec2},
+};
+
+use super::{BlendMode, Canvas, Color, DrawParams, FilterMode};
+
+/// Number of rays cast around a point light, before the rays cast at occluder corners.
+const LIGHT_RAYS: usize = 64;
+
+/// Number of bands a light is split into from its center to its edge to follow its falloff.
+const FALLOFF_STEPS: usize = 8;
+
+/// A light added to a [LightMap].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Light {
+    pub position: Vec2,
+    pub color: Color,
+    /// Distance the light reaches.
+    pub radius: f32,
+    /// Brightness at the center of the light.
+    pub intensity: f32,
+    /// How quickly the light dims towards its edge. 1 dims evenly, higher values dim faster.
+    pub falloff: f32,
+    pub kind: LightKind,
+    /// Whether occluders block the light.
+    pub shadows: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LightKind {
+    /// Shines in every direction.
+    Point,
+    /// Shines in a cone, with the direction and width of the cone in radians.
+    Spot { direction: f32, angle: f32 },
+}
+
+impl Light {
+    pub fn point(position: Vec2, radius: f32, color: Color) -> Self {
+        Self {
+            position,
+            color,
+            radius,
+            intensity: 1.0,
+            falloff: 2.0,
+            kind: LightKind::Point,
+            shadows: true,
+        }
+    }
+
+    pub fn spot(position: Vec2, radius: f32, color: Color, direction: f32, angle: f32) -> Self {
+        Self {
+            kind: LightKind::Spot { direction, angle },
+            ..Self::point(position, radius, color)
+        }
+    }
+
+    /// Brightness of the light's color at a distance from its center.
+    fn color_at(&self, distance: f32) -> Color {
+        let brightness =
+            self.intensity * (1.0 - distance / self.radius).max(0.0).powf(self.falloff);
+        Color::new(
+            self.color.r * brightness,
+            self.color.g * brightness,
+            self.color.b * brightness,
+            self.color.a,
+        )
+    }
+
+    /// Start and end of the angles the light shines in.
+    fn angles(&self) -> (f32, f32) {
+        match self.kind {
+            LightKind::Point => (0.0, TAU),
+            LightKind::Spot { direction, angle } => {
+                let angle = angle.clamp(0.0, TAU);
+                (direction - angle / 2.0, direction + angle / 2.0)
+            }
+        }
+    }
+}
+
+/// A shape that blocks light, casting hard shadows.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Occluder {
+    Rectangle(Rectangle),
+    /// A closed polygon.
+    Polygon(Vec<Vec2>),
+    /// A line that light does not pass through, such as a thin wall.
+    Segment(Vec2, Vec2),
+}
+
+impl Occluder {
+    fn segments(&self) -> Vec<(Vec2, Vec2)> {
+        match self {
+            Self::Rectangle(rectangle) => {
+                let corners = [
+                    vec2(rectangle.x, rectangle.y),
+                    vec2(rectangle.x + rectangle.w, rectangle.y),
+                    vec2(rectangle.x + rectangle.w, rectangle.y + rectangle.h),
+                    vec2(rectangle.x, rectangle.y + rectangle.h),
+                ];
+                (0..4).map(|i| (corners[i], corners[(i + 1) % 4])).collect()
+            }
+            Self::Polygon(points) => (0..points.len())
+                .map(|i| (points[i], points[(i + 1) % points.len()]))
+                .collect(),
+            Self::Segment(a, b) => vec![(*a, *b)],
+        }
+    }
+}
+
+/// Lights rendered together into a texture that darkens the scene outside of them.
+///
+/// Draw the scene first, then the light map over it with [LightMap::draw],
+/// which also draws anything queued with a [DrawOrder](super::DrawOrder) before it.
+#[derive(Debug, Clone)]
+pub struct LightMap {
+    canvas: Canvas,
+    /// Light reaching every part of the scene. Black leaves only what lights reach visible.
+    pub ambient: Color,
+    pub lights: Vec<Light>,
+    pub occluders: Vec<Occluder>,
+}
+
+impl LightMap {
+    /// Creates a light map with a texture of the given size,
+    /// which is stretched over the area being lit.
+    pub fn new(ctx: &mut Context, width: u32, height: u32) -> Self {
+        let canvas = Canvas::new(ctx, width, height);
+        canvas.texture().set_filter(FilterMode::Linear);
+        Self {
+            canvas,
+            ambient: Color::new(0.0, 0.0, 0.0, 1.0),
+            lights: Vec::new(),
+            occluders: Vec::new(),
+        }
+    }
+
+    pub fn with_ambient(mut self, ambient: Color) -> Self {
+        self.ambient = ambient;
+        self
+    }
+
+    /// The texture lights are rendered to.
+    pub fn canvas(&self) -> &Canvas {
+        &self.canvas
+    }
+
+    /// Renders the lights within an area of the world to the light map.
+    ///
+    /// Flushes the [draw queue](super::flush_draw_queue), so draw calls queued before this
+    /// are not sorted with ones queued after it.
+    pub fn render(&self, ctx: &mut Context, area: Rectangle) {
+        let previous = ctx.canvas.clone();
+
+        super::set_canvas(ctx, &self.canvas);
+        super::clear(ctx, self.ambient);
+
+        let scale = self.canvas.size() / area.size();
+        let segments = self
+            .occluders
+            .iter()
+            .flat_map(Occluder::segments)
+            .collect::<Vec<_>>();
+
+        for light in &self.lights {
+            let bounds = Rectangle::new(
+                light.position.x - light.radius,
+                light.position.y - light.radius,
+                light.radius * 2.0,
+                light.radius * 2.0,
+            );
+            if light.radius <= 0.0 || !bounds.overlaps(&area) {
+                continue;
+            }
+
+            let rays = match light.shadows {
+                true => rays(light, &segments),
+                false => rays(light, &[]),
+            };
+            let (vertices, indices) =
+                geometry(light, &rays, |point| (point - area.point()) * scale);
+            super::draw_geometry(ctx, None, Some(BlendMode::Additive), &vertices, &indices);
+        }
+
+        match previous {
+            Some(canvas) => super::set_canvas(ctx, &canvas),
+            None => super::reset_canvas(ctx),
+        }
+    }
+
+    /// Renders the lights and multiplies everything visible by the light map.
+    ///
+    /// Rendering flushes the [draw queue](super::flush_draw_queue), so call this after
+    /// every draw call of the scene has been queued. Ones queued later are drawn over the lighting.
+    pub fn draw(&self, ctx: &mut Context) {
+        let area = super::visible_world(ctx);
+        self.render(ctx, area);
+        self.canvas.draw(
+            ctx,
+            area.x,
+            area.y,
+            DrawParams {
+                dest_size: Some(area.size()),
+                blend: Some(BlendMode::Multiply),
+                ..Default::default()
+            },
+        );
+    }
+}
+
+/// Casts rays from a light, returning the direction of each ray
+/// and the distance it travels before hitting a segment or the edge of the light.
+fn rays(light: &Light, segments: &[(Vec2, Vec2)]) -> Vec<(Vec2, f32)> {
+    let (start, end) = light.angles();
+    let span = end - start;
+
+    let nearby = segments
+        .iter()
+        .filter(|(a, b)| distance_to_segment(light.position, *a, *b) < light.radius)
+        .collect::<Vec<_>>();
+
+    let steps = ((LIGHT_RAYS as f32 * span / TAU).ceil() as usize).max(1);
+    let mut angles = (0..=steps)
+        .map(|i| span * i as f32 / steps as f32)
+        .collect::<Vec<_>>();
+
+    // rays just either side of each corner so shadows start exactly at it
+    for point in nearby.iter().flat_map(|(a, b)| [a, b]) {
+        let offset = *point - light.position;
+        let angle = offset.y.atan2(offset.x);
+        for angle in [angle - 0.0001, angle, angle + 0.0001] {
+            let relative = (angle - start).rem_euclid(TAU);
+            if relative <= span {
+                angles.push(relative);
+            }
+        }
+    }
+
+    angles.sort_by(f32::total_cmp);
+    angles.dedup_by(|a, b| (*a - *b).abs() < 0.00001);
+
+    angles
+        .into_iter()
+        .map(|angle| {
+            let direction = vec2((start + angle).cos(), (start + angle).sin());
+            let distance = nearby
+                .iter()
+                .filter_map(|(a, b)| intersect(light.position, direction, *a, *b))
+                .fold(light.radius, f32::min);
+            (direction, distance)
+        })
+        .collect()
+}
+
+/// Bands of triangles between each pair of rays, colored with the light's falloff.
+/// Lights with many rays are split over several draw calls by [draw_geometry](super::draw_geometry).
+fn geometry(
+    light: &Light,
+    rays: &[(Vec2, f32)],
+    transform: impl Fn(Vec2) -> Vec2,
+) -> (Vec<Vertex>, Vec<u32>) {
+    let vertices = rays
+        .iter()
+        .flat_map(|(direction, length)| {
+            (0..=FALLOFF_STEPS).map(move |step| {
+                let distance = (light.radius * step as f32 / FALLOFF_STEPS as f32).min(*length);
+                (light.position + *direction * distance, distance)
+            })
+        })
+        .map(|(point, distance)| {
+            let point = transform(point);
+            Vertex::new(point.x, point.y, 0.0, 0.0, 0.0, light.color_at(distance))
+        })
+        .collect();
+
+    let row = FALLOFF_STEPS as u32 + 1;
+    let indices = (0..rays.len().saturating_sub(1) as u32)
+        .flat_map(|ray| {
+            (0..FALLOFF_STEPS as u32).flat_map(move |step| {
+                let a = ray * row + step;
+                let b = a + row;
+                [a, a + 1, b + 1, a, b + 1, b]
+            })
+        })
+        .collect();
+
+    (vertices, indices)
+}
+
+/// Distance along a ray to where it crosses a segment.
+fn intersect(origin: Vec2, direction: Vec2, a: Vec2, b: Vec2) -> Option<f32> {
+    let edge = b - a;
+    let denominator = direction.perp_dot(edge);
+    if denominator.abs() <= f32::EPSILON {
+        return None;
+    }
+    let to_start = a - origin;
+    let distance = to_start.perp_dot(edge) / denominator;
+    let along = to_start.perp_dot(direction) / denominator;
+    (distance >= 0.0 && (0.0..=1.0).contains(&along)).then_some(distance)
+}
+
+fn distance_to_segment(point: Vec2, a: Vec2, b: Vec2) -> f32 {
+    let edge = b - a;
+    let t = match edge.length_squared() {
+        length if length > 0.0 => ((point - a).dot(edge) / length).clamp(0.0, 1.0),
+        _ => 0.0,
+    };
+    point.distance(a + edge * t)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::f32::consts::{FRAC_PI_2, FRAC_PI_4, PI};
+
+    use super::*;
+
+    fn angle(direction: Vec2) -> f32 {
+        direction.y.atan2(direction.x)
+    }
+
+    /// Distance of the ray closest to the angle.
+    fn ray_at(rays: &[(Vec2, f32)], target: f32) -> f32 {
+        rays.iter()
+            .min_by(|(a, _), (b, _)| {
+                let off = |direction: &Vec2| (angle(*direction) - target).abs();
+                off(a).total_cmp(&off(b))
+            })
+            .map(|(_, distance)| *distance)
+            .unwrap()
+    }
+
+    #[test]
+    fn intersects() {
+        let (a, b) = (vec2(2.0, -1.0), vec2(2.0, 1.0));
+        assert_eq!(intersect(Vec2::ZERO, vec2(1.0, 0.0), a, b), Some(2.0));
+        // ends of the segment count as hits
+        assert_eq!(intersect(Vec2::ZERO, vec2(2.0, 1.0), a, b), Some(1.0));
+        // behind, past the end and parallel
+        assert_eq!(intersect(Vec2::ZERO, vec2(-1.0, 0.0), a, b), None);
+        assert_eq!(
+            intersect(Vec2::ZERO, vec2(1.0, 1.0).normalize(), a, b),
+            None
+        );
+        assert_eq!(intersect(Vec2::ZERO, vec2(0.0, 1.0), a, b), None);
+        assert_eq!(intersect(Vec2::ZERO, vec2(1.0, 0.0), a, a), None);
+    }
+
+    #[test]
+    fn distance_to_segments() {
+        let (a, b) = (vec2(0.0, 0.0), vec2(4.0, 0.0));
+        assert_eq!(distance_to_segment(vec2(2.0, 3.0), a, b), 3.0);
+        assert_eq!(distance_to_segment(vec2(-3.0, 4.0), a, b), 5.0);
+        assert_eq!(distance_to_segment(vec2(7.0, -4.0), a, b), 5.0);
+        assert_eq!(distance_to_segment(vec2(3.0, 4.0), a, a), 5.0);
+    }
+
+    #[test]
+    fn rays_around_corners() {
+        let light = Light::point(Vec2::ZERO, 10.0, Color::WHITE);
+        assert_eq!(rays(&light, &[]).len(), LIGHT_RAYS + 1);
+
+        let wall = (vec2(2.0, -1.0), vec2(2.0, 1.0));
+        let rays = rays(&light, &[wall]);
+        assert_eq!(rays.len(), LIGHT_RAYS + 1 + 6);
+
+        // shadows start at the corners
+        let corner = 0.5f32.atan();
+        assert!((ray_at(&rays, corner - 0.0001) - 5f32.sqrt()).abs() < 1e-3);
+        assert_eq!(ray_at(&rays, corner + 0.0001), 10.0);
+        assert_eq!(ray_at(&rays, 0.0), 2.0);
+        assert_eq!(ray_at(&rays, PI), 10.0);
+
+        // walls out of reach add no rays
+        let far = (vec2(20.0, -1.0), vec2(20.0, 1.0));
+        assert_eq!(super::rays(&light, &[far]).len(), LIGHT_RAYS + 1);
+    }
+
+    #[test]
+    fn spot_rays_wrap() {
+        let wall = (vec2(-5.0, -1.0), vec2(-5.0, 0.5));
+        let light = Light::spot(Vec2::ZERO, 10.0, Color::WHITE, PI, FRAC_PI_2);
+        let unblocked = super::rays(&light, &[]).len();
+        let rays = rays(&light, &[wall]);
+
+        // the cone crosses from positive to negative angles behind the light
+        for (direction, _) in &rays {
+            assert!(
+                angle(*direction).abs() >= PI - FRAC_PI_4 - 1e-4,
+                "{:?}",
+                direction
+            );
+        }
+        let below = -PI + 0.2f32.atan();
+        let above = PI - 0.1f32.atan();
+        assert!((ray_at(&rays, below - 0.0001) - 26f32.sqrt()).abs() < 1e-3);
+        assert_eq!(ray_at(&rays, below + 0.0001), 10.0);
+        assert!((ray_at(&rays, above + 0.0001) - 25.25f32.sqrt()).abs() < 1e-3);
+        assert_eq!(ray_at(&rays, above - 0.0001), 10.0);
+        assert_eq!(rays.len(), unblocked + 6);
+
+        // corners outside of the cone are skipped
+        let behind = (vec2(5.0, -1.0), vec2(5.0, 1.0));
+        assert_eq!(super::rays(&light, &[behind]).len(), unblocked);
+    }
+}
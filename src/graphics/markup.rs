@@ -0,0 +1,383 @@
+use crate::{context::Context, error::ParseError, math::Vec2};
+
+use super::{Color, ColorExt, Font, TextParams};
+
+/// Text with inline formatting, parsed from markup.
+///
+/// Supported tags are `[color=red]` or `[color=#ff8800]` until `[/color]`,
+/// and `[wait=0.5]` to pause a [TextReveal] for a number of seconds.
+/// A wait at the end of the text delays the reveal from finishing.
+/// `[[` is a literal `[`.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct RichText {
+    text: String,
+    chars: Vec<RichChar>,
+    /// Time to wait after the last character is revealed.
+    end_wait: f32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct RichChar {
+    /// Byte position of the character in the text.
+    index: usize,
+    color: Option<Color>,
+    /// Time to wait before the character is revealed.
+    wait: f32,
+}
+
+/// Reveals text one character at a time.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextReveal {
+    /// Characters revealed per second.
+    pub speed: f32,
+    visible: usize,
+    timer: f32,
+    /// Whether the wait at the end of the text has passed.
+    ended: bool,
+}
+
+impl RichText {
+    pub fn parse(markup: &str) -> Result<Self, ParseError> {
+        const FORMAT: &str = "text markup";
+
+        let mut text = String::with_capacity(markup.len());
+        let mut chars = Vec::with_capacity(markup.len());
+        let mut colors = Vec::new();
+        let mut wait = 0.0;
+
+        let mut rest = markup;
+
+        while let Some(character) = rest.chars().next() {
+            if character == '[' {
+                if let Some(escaped) = rest.strip_prefix("[[") {
+                    rest = escaped;
+                } else {
+                    let end = rest
+                        .find(']')
+                        .ok_or_else(|| ParseError::new(FORMAT, "unclosed tag"))?;
+                    let tag = &rest[1..end];
+                    rest = &rest[end + 1..];
+
+                    let (name, value) = match tag.split_once('=') {
+                        Some((name, value)) => (name.trim(), Some(value.trim())),
+                        None => (tag.trim(), None),
+                    };
+
+                    match (name, value) {
+                        ("color", Some(value)) => {
+                            colors.push(parse_color(value).ok_or_else(|| {
+                                ParseError::new(FORMAT, format!("unknown color \"{}\"", value))
+                            })?)
+                        }
+                        ("/color", None) => {
+                            colors.pop();
+                        }
+                        ("wait", Some(value)) => {
+                            wait += value.parse::<f32>().map_err(|_| {
+                                ParseError::new(FORMAT, format!("invalid wait \"{}\"", value))
+                            })?
+                        }
+                        _ => {
+                            return Err(ParseError::new(
+                                FORMAT,
+                                format!("unknown tag \"[{}]\"", tag),
+                            ))
+                        }
+                    }
+                    continue;
+                }
+            } else {
+                rest = &rest[character.len_utf8()..];
+            }
+
+            chars.push(RichChar {
+                index: text.len(),
+                color: colors.last().copied(),
+                wait,
+            });
+            text.push(character);
+            wait = 0.0;
+        }
+
+        Ok(Self {
+            text,
+            chars,
+            end_wait: wait,
+        })
+    }
+
+    /// The text without any markup.
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Number of characters in the text.
+    pub fn len(&self) -> usize {
+        self.chars.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.chars.is_empty()
+    }
+
+    fn char_at(&self, index: usize) -> Option<(usize, &RichChar)> {
+        self.chars
+            .binary_search_by_key(&index, |c| c.index)
+            .ok()
+            .map(|position| (position, &self.chars[position]))
+    }
+}
+
+impl TextReveal {
+    pub fn new(speed: f32) -> Self {
+        Self {
+            speed,
+            visible: 0,
+            timer: 0.0,
+            ended: false,
+        }
+    }
+
+    /// Reveals characters for the time passed,
+    /// calling `on_char` with each character as it is revealed.
+    pub fn update(&mut self, text: &RichText, delta: f32, mut on_char: impl FnMut(char)) {
+        self.timer += delta;
+
+        let step = match self.speed > 0.0 {
+            true => 1.0 / self.speed,
+            false => 0.0,
+        };
+
+        while let Some(next) = text.chars.get(self.visible) {
+            let time = next.wait + step;
+            if self.timer < time {
+                break;
+            }
+            self.timer -= time;
+            self.visible += 1;
+            if let Some(character) = text.text[next.index..].chars().next() {
+                on_char(character);
+            }
+        }
+
+        if self.visible >= text.len() && self.timer >= text.end_wait {
+            self.ended = true;
+        }
+
+        if self.finished(text) {
+            self.timer = 0.0;
+        }
+    }
+
+    /// Number of characters revealed.
+    pub fn visible(&self) -> usize {
+        self.visible
+    }
+
+    /// Whether every character of the text has been revealed.
+    pub fn finished(&self, text: &RichText) -> bool {
+        self.visible >= text.len() && (self.ended || text.end_wait <= 0.0)
+    }
+
+    /// Reveals the rest of the text immediately.
+    pub fn skip(&mut self, text: &RichText) {
+        self.visible = text.len();
+        self.timer = 0.0;
+        self.ended = true;
+    }
+
+    /// Hides all of the text again, for use with the next page.
+    pub fn reset(&mut self) {
+        self.visible = 0;
+        self.timer = 0.0;
+        self.ended = false;
+    }
+}
+
+/// Draws rich text, only drawing the first `visible` characters if given.
+/// Characters without a color use the color of the text parameters.
+pub fn draw_rich_text(
+    ctx: &mut Context,
+    font: &Font,
+    text: &RichText,
+    x: f32,
+    y: f32,
+    params: TextParams,
+    visible: Option<usize>,
+) {
+    let visible = visible.unwrap_or(usize::MAX);
+    let line_height = (font.line_height() + params.line_spacing) * params.scale;
+
+    for (line_index, line) in font
+        .wrap(&text.text, params.wrap, params.scale)
+        .into_iter()
+        .enumerate()
+    {
+        let start = line.as_ptr() as usize - text.text.as_ptr() as usize;
+        let mut x = x + params.line_offset(font.line_width(line) * params.scale);
+        let y = y + line_index as f32 * line_height;
+        let mut previous = None;
+
+        for (offset, character) in line.char_indices() {
+            let (position, rich) = match text.char_at(start + offset) {
+                Some(rich) => rich,
+                None => continue,
+            };
+            if position >= visible {
+                return;
+            }
+            let kerning = font.kerning(previous, character) * params.scale;
+            font.draw_char(
+                ctx,
+                character,
+                x + kerning,
+                y,
+                rich.color.unwrap_or(params.color),
+                params.scale,
+            );
+            x += font.advance(character, previous) * params.scale;
+            previous = Some(character);
+        }
+    }
+}
+
+/// Size of the area rich text is drawn in.
+pub fn measure_rich_text(ctx: &Context, font: &Font, text: &RichText, params: TextParams) -> Vec2 {
+    super::measure_text(ctx, font, &text.text, params)
+}
+
+/// Reads a color name or a hex color written as `#rrggbb` or `#rrggbbaa`.
+pub(crate) fn parse_color(value: &str) -> Option<Color> {
+    if value.starts_with('#') {
+        return Color::parse_hex(value);
+    }
+    Some(match value {
+        "white" => Color::WHITE,
+        "black" => Color::BLACK,
+        "red" => Color::RED,
+        "green" => Color::GREEN,
+        "blue" => Color::BLUE,
+        "yellow" => Color::YELLOW,
+        "magenta" => Color::MAGENTA,
+        "gray" | "grey" => Color::GRAY,
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn colors(text: &RichText) -> Vec<Option<Color>> {
+        text.chars.iter().map(|c| c.color).collect()
+    }
+
+    fn waits(text: &RichText) -> Vec<f32> {
+        text.chars.iter().map(|c| c.wait).collect()
+    }
+
+    #[test]
+    fn plain() {
+        let text = RichText::parse("héllo").unwrap();
+        assert_eq!(text.text(), "héllo");
+        assert_eq!(text.len(), 5);
+        assert_eq!(
+            text.chars.iter().map(|c| c.index).collect::<Vec<_>>(),
+            [0, 1, 3, 4, 5]
+        );
+        assert!(RichText::parse("").unwrap().is_empty());
+    }
+
+    #[test]
+    fn escapes() {
+        let text = RichText::parse("[[a] [[[color=red]b[/color]").unwrap();
+        assert_eq!(text.text(), "[a] [b");
+        assert_eq!(colors(&text)[5], Some(Color::RED));
+    }
+
+    #[test]
+    fn colors_nest() {
+        let text =
+            RichText::parse("a[color=red]b[color=#0000ff]c[/color]d[/color]e[/color]f").unwrap();
+        assert_eq!(text.text(), "abcdef");
+        assert_eq!(
+            colors(&text),
+            [
+                None,
+                Some(Color::RED),
+                Some(Color::from_rgba(0, 0, 255, 255)),
+                Some(Color::RED),
+                None,
+                None
+            ]
+        );
+    }
+
+    #[test]
+    fn waits_add_up() {
+        let text = RichText::parse("a[wait=0.5][wait = 0.25]b c[wait=1]").unwrap();
+        assert_eq!(waits(&text), [0.0, 0.75, 0.0, 0.0]);
+        assert_eq!(text.end_wait, 1.0);
+    }
+
+    #[test]
+    fn rejects() {
+        for markup in [
+            "[color=red",
+            "a[",
+            "[color=]",
+            "[color=nope]",
+            "[color=#12]",
+            "[color]",
+            "[/color=red]",
+            "[wait]",
+            "[wait=soon]",
+            "[bold]",
+        ] {
+            assert!(RichText::parse(markup).is_err(), "{}", markup);
+        }
+    }
+
+    #[test]
+    fn reveal() {
+        let text = RichText::parse("ab[wait=1]c").unwrap();
+        let mut reveal = TextReveal::new(2.0);
+        let mut revealed = String::new();
+
+        reveal.update(&text, 1.0, |c| revealed.push(c));
+        assert_eq!(revealed, "ab");
+        assert!(!reveal.finished(&text));
+
+        reveal.update(&text, 1.0, |c| revealed.push(c));
+        assert_eq!(revealed, "ab");
+        reveal.update(&text, 0.5, |c| revealed.push(c));
+        assert_eq!(revealed, "abc");
+        assert_eq!(reveal.visible(), 3);
+        assert!(reveal.finished(&text));
+
+        reveal.reset();
+        assert_eq!(reveal.visible(), 0);
+        reveal.skip(&text);
+        assert!(reveal.finished(&text));
+    }
+
+    #[test]
+    fn reveal_waits_at_end() {
+        let text = RichText::parse("a[wait=1]").unwrap();
+        let mut reveal = TextReveal::new(1.0);
+
+        reveal.update(&text, 1.0, |_| ());
+        assert_eq!(reveal.visible(), 1);
+        assert!(!reveal.finished(&text));
+        reveal.update(&text, 0.5, |_| ());
+        assert!(!reveal.finished(&text));
+        reveal.update(&text, 0.5, |_| ());
+        assert!(reveal.finished(&text));
+
+        reveal.reset();
+        assert!(!reveal.finished(&text));
+        reveal.skip(&text);
+        assert!(reveal.finished(&text));
+    }
+}
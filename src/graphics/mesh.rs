@@ -0,0 +1,282 @@
+use macroquad::prelude::{get_internal_gl, DrawMode, Mat4, Vertex};
+use std::borrow::Cow;
+
+use crate::{context::Context, math::Vec2};
+
+use super::{BlendMode, Color, Texture};
+
+/// Vertex and index limits of a single geometry call, the defaults set in macroquad's
+/// `QuadGl::new` (`src/quad_gl.rs`). Calls reaching either limit are clamped with a warning.
+const MAX_VERTICES: usize = 10000;
+const MAX_INDICES: usize = 5000;
+
+/// Indices submitted in each call when geometry is over the limits, a whole number of triangles.
+const INDICES_PER_CALL: usize = (MAX_INDICES - 1) - (MAX_INDICES - 1) % 3;
+
+/// A point of a [Mesh].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MeshVertex {
+    pub position: Vec2,
+
+    /// Texture coordinates, from 0 to 1 across the texture.
+    pub uv: Vec2,
+
+    pub color: Color,
+}
+
+impl MeshVertex {
+    pub const fn new(position: Vec2, uv: Vec2, color: Color) -> Self {
+        Self {
+            position,
+            uv,
+            color,
+        }
+    }
+
+    fn prepare(&self) -> Vertex {
+        Vertex::new(
+            self.position.x,
+            self.position.y,
+            0.0,
+            self.uv.x,
+            self.uv.y,
+            self.color,
+        )
+    }
+}
+
+/// How often the geometry of a [Mesh] changes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MeshUsage {
+    /// Set once and drawn many times. The vertices are prepared for drawing once per change.
+    Static,
+    /// Changed about as often as it is drawn, such as trails rebuilt every frame.
+    Dynamic,
+}
+
+/// Triangles made of vertices with a position, texture coordinates and color,
+/// optionally textured.
+///
+/// Every three indices form a triangle. Triangles with an index past the end of the vertices
+/// are skipped when drawing.
+/// Meshes with more vertices than macroquad draws at once are split over several draw calls.
+#[derive(Debug, Clone)]
+pub struct Mesh {
+    vertices: Vec<MeshVertex>,
+    indices: Vec<u32>,
+    texture: Option<Texture>,
+    usage: MeshUsage,
+    prepared: Vec<Vertex>,
+    dirty: bool,
+}
+
+impl Mesh {
+    pub fn new(vertices: Vec<MeshVertex>, indices: Vec<u32>, usage: MeshUsage) -> Self {
+        Self {
+            vertices,
+            indices,
+            texture: None,
+            usage,
+            prepared: Vec::new(),
+            dirty: true,
+        }
+    }
+
+    /// Creates a mesh with no geometry, to be built with [Mesh::extend].
+    pub fn empty(usage: MeshUsage) -> Self {
+        Self::new(Vec::new(), Vec::new(), usage)
+    }
+
+    pub fn with_texture(mut self, texture: Texture) -> Self {
+        self.texture = Some(texture);
+        self
+    }
+
+    pub fn texture(&self) -> Option<&Texture> {
+        self.texture.as_ref()
+    }
+
+    pub fn set_texture(&mut self, texture: Option<Texture>) {
+        self.texture = texture;
+    }
+
+    pub fn usage(&self) -> MeshUsage {
+        self.usage
+    }
+
+    pub fn vertices(&self) -> &[MeshVertex] {
+        &self.vertices
+    }
+
+    pub fn vertices_mut(&mut self) -> &mut Vec<MeshVertex> {
+        self.dirty = true;
+        &mut self.vertices
+    }
+
+    pub fn indices(&self) -> &[u32] {
+        &self.indices
+    }
+
+    pub fn indices_mut(&mut self) -> &mut Vec<u32> {
+        &mut self.indices
+    }
+
+    /// Replaces the geometry of the mesh.
+    pub fn set(&mut self, vertices: &[MeshVertex], indices: &[u32]) {
+        self.clear();
+        self.extend(vertices, indices);
+    }
+
+    /// Adds geometry to the mesh. The indices are relative to the added vertices.
+    pub fn extend(&mut self, vertices: &[MeshVertex], indices: &[u32]) {
+        let base = self.vertices.len() as u32;
+        self.vertices.extend_from_slice(vertices);
+        self.indices
+            .extend(indices.iter().map(|index| base + index));
+        self.dirty = true;
+    }
+
+    pub fn clear(&mut self) {
+        self.vertices.clear();
+        self.indices.clear();
+        self.dirty = true;
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.indices.is_empty()
+    }
+
+    /// Draws the mesh moved by the given offset,
+    /// with the blend mode set with [set_blend_mode](super::set_blend_mode).
+    pub fn draw(&mut self, ctx: &mut Context, x: f32, y: f32) {
+        let texture = self.texture.as_ref();
+
+        let dynamic;
+        let vertices = match self.usage {
+            MeshUsage::Static => {
+                if self.dirty {
+                    self.prepared.clear();
+                    self.prepared
+                        .extend(self.vertices.iter().map(MeshVertex::prepare));
+                    self.dirty = false;
+                }
+                &self.prepared
+            }
+            MeshUsage::Dynamic => {
+                dynamic = self
+                    .vertices
+                    .iter()
+                    .map(MeshVertex::prepare)
+                    .collect::<Vec<_>>();
+                &dynamic
+            }
+        };
+
+        unsafe { get_internal_gl() }
+            .quad_gl
+            .push_model_matrix(Mat4::from_translation([x, y, 0.0].into()));
+        draw_geometry(ctx, texture, None, vertices, &self.indices);
+        unsafe { get_internal_gl() }.quad_gl.pop_model_matrix();
+    }
+}
+
+/// Types of indices triangles can be drawn with.
+pub(crate) trait GeometryIndex: Copy {
+    fn index(self) -> usize;
+
+    /// The indices in the type macroquad takes.
+    /// Only called with indices below [MAX_VERTICES].
+    fn narrow(indices: &[Self]) -> Cow<'_, [u16]>;
+}
+
+impl GeometryIndex for u16 {
+    fn index(self) -> usize {
+        self as usize
+    }
+
+    fn narrow(indices: &[Self]) -> Cow<'_, [u16]> {
+        Cow::Borrowed(indices)
+    }
+}
+
+impl GeometryIndex for u32 {
+    fn index(self) -> usize {
+        self as usize
+    }
+
+    fn narrow(indices: &[Self]) -> Cow<'_, [u16]> {
+        Cow::Owned(indices.iter().map(|index| *index as u16).collect())
+    }
+}
+
+/// Draws triangles, splitting them over several calls if needed.
+///
+/// Triangles with an index past the end of the vertices are skipped,
+/// as are indices left over after the last whole triangle.
+pub(crate) fn draw_geometry<I: GeometryIndex>(
+    ctx: &mut Context,
+    texture: Option<&Texture>,
+    blend: Option<BlendMode>,
+    vertices: &[Vertex],
+    indices: &[I],
+) {
+    let indices = valid_triangles(vertices.len(), indices);
+    if indices.is_empty() {
+        return;
+    }
+
+    super::apply_blend_mode(ctx, blend);
+
+    let gl = unsafe { get_internal_gl() }.quad_gl;
+    gl.texture(texture.map(|texture| **texture.data()));
+    gl.draw_mode(DrawMode::Triangles);
+
+    if indices.len() < MAX_INDICES && vertices.len() < MAX_VERTICES {
+        gl.geometry(vertices, &I::narrow(&indices));
+    } else {
+        for chunk in indices.chunks(INDICES_PER_CALL) {
+            let chunk_vertices = chunk
+                .iter()
+                .map(|index| vertices[index.index()])
+                .collect::<Vec<_>>();
+            let chunk_indices = (0..chunk.len() as u16).collect::<Vec<_>>();
+            gl.geometry(&chunk_vertices, &chunk_indices);
+        }
+    }
+
+    gl.texture(None);
+}
+
+/// The indices of the triangles whose indices are all within the vertices.
+fn valid_triangles<I: GeometryIndex>(vertices: usize, indices: &[I]) -> Cow<'_, [I]> {
+    let whole = &indices[..indices.len() - indices.len() % 3];
+    if whole.iter().all(|index| index.index() < vertices) {
+        return Cow::Borrowed(whole);
+    }
+    Cow::Owned(
+        whole
+            .chunks(3)
+            .filter(|triangle| triangle.iter().all(|index| index.index() < vertices))
+            .flatten()
+            .copied()
+            .collect(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn skips_invalid_triangles() {
+        let indices: [u32; 11] = [0, 1, 2, 2, 3, 0, 0, 1, 65536, 1, 2];
+        assert_eq!(&*valid_triangles(4, &indices), [0, 1, 2, 2, 3, 0]);
+        assert_eq!(&*valid_triangles(3, &indices[..6]), [0, 1, 2]);
+        assert!(matches!(
+            valid_triangles(4, &indices[..6]),
+            Cow::Borrowed(_)
+        ));
+        assert!(valid_triangles::<u16>(0, &[0, 1, 2]).is_empty());
+    }
+}
@@ -0,0 +1,265 @@
+use macroquad::prelude::{get_internal_gl, Mat4};
+
+use crate::{
+    context::Context,
+    math::{vec2, Rectangle},
+};
+
+use super::{BlendMode, Color, DrawParams, Texture};
+
+/// Draws a texture at any size while keeping its borders intact,
+/// useful for panels, buttons and message boxes.
+///
+/// The source is split into nine parts by the insets. The corners are drawn as they are,
+/// the edges and center are stretched or tiled to fill the rest.
+#[derive(Debug, Clone)]
+pub struct NineSlice {
+    texture: Texture,
+    /// Part of the texture to use. If None - use the whole texture.
+    pub source: Option<Rectangle>,
+    pub insets: Insets,
+    /// How the top, bottom, left and right edges fill their space.
+    pub edges: SliceMode,
+    /// How the center fills its space.
+    pub center: SliceMode,
+}
+
+/// Size of the borders of a [NineSlice] in pixels.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Insets {
+    pub left: f32,
+    pub top: f32,
+    pub right: f32,
+    pub bottom: f32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SliceMode {
+    Stretch,
+    /// Repeat the part at its original size, cutting off the last repeat.
+    Tile,
+}
+
+impl Insets {
+    pub fn new(left: f32, top: f32, right: f32, bottom: f32) -> Self {
+        Self {
+            left,
+            top,
+            right,
+            bottom,
+        }
+    }
+
+    /// Insets with the same size on every side.
+    pub fn uniform(size: f32) -> Self {
+        Self::new(size, size, size, size)
+    }
+}
+
+impl NineSlice {
+    pub fn new(texture: Texture, insets: Insets) -> Self {
+        Self {
+            texture,
+            source: None,
+            insets,
+            edges: SliceMode::Stretch,
+            center: SliceMode::Stretch,
+        }
+    }
+
+    pub fn with_source(mut self, source: Rectangle) -> Self {
+        self.source = Some(source);
+        self
+    }
+
+    pub fn with_mode(mut self, edges: SliceMode, center: SliceMode) -> Self {
+        self.edges = edges;
+        self.center = center;
+        self
+    }
+
+    pub fn texture(&self) -> &Texture {
+        &self.texture
+    }
+
+    /// Draws the nine-slice to fill the given area.
+    /// Borders are shrunk if the area is too small to fit them.
+    ///
+    /// The whole nine-slice is flipped within the area
+    /// and rotated around the origin, which is the center of the area by default.
+    /// A source in the parameters replaces the nine-slice's source.
+    /// The destination size of the parameters is unused.
+    pub fn draw(
+        &self,
+        ctx: &mut Context,
+        x: f32,
+        y: f32,
+        width: f32,
+        height: f32,
+        params: DrawParams,
+    ) {
+        if let Some(order) = params.order {
+            let slice = self.clone();
+            let params = DrawParams {
+                order: None,
+                ..params
+            };
+            return super::draw_deferred(ctx, order, move |ctx| {
+                slice.draw(ctx, x, y, width, height, params)
+            });
+        }
+
+        let area = Rectangle::new(x, y, width, height);
+        let source = params.source.or(self.source);
+        let center = vec2(x + width / 2.0, y + height / 2.0);
+        let origin = params.origin.unwrap_or(center);
+        let flip = |flip: bool| if flip { -1.0 } else { 1.0 };
+        let transform = Mat4::from_translation([origin.x, origin.y, 0.0].into())
+            * Mat4::from_rotation_z(params.rotation)
+            * Mat4::from_translation([center.x - origin.x, center.y - origin.y, 0.0].into())
+            * Mat4::from_scale([flip(params.flip_x), flip(params.flip_y), 1.0].into())
+            * Mat4::from_translation([-center.x, -center.y, 0.0].into());
+
+        unsafe { get_internal_gl() }
+            .quad_gl
+            .push_model_matrix(transform);
+        self.draw_parts(ctx, source, area, params.color, params.blend);
+        unsafe { get_internal_gl() }.quad_gl.pop_model_matrix();
+    }
+
+    fn draw_parts(
+        &self,
+        ctx: &mut Context,
+        source: Option<Rectangle>,
+        area: Rectangle,
+        color: Color,
+        blend: Option<BlendMode>,
+    ) {
+        let Rectangle {
+            x,
+            y,
+            w: width,
+            h: height,
+        } = area;
+        let source = source.unwrap_or_else(|| {
+            Rectangle::new(0.0, 0.0, self.texture.width(), self.texture.height())
+        });
+        let insets = self.insets;
+
+        let fit = |start: f32, end: f32, size: f32| match start + end > size && start + end > 0.0 {
+            true => size / (start + end),
+            false => 1.0,
+        };
+        let scale = vec2(
+            fit(insets.left, insets.right, width),
+            fit(insets.top, insets.bottom, height),
+        );
+
+        // source and destination position and size of each column and row
+        let columns = [
+            (source.x, insets.left, x, insets.left * scale.x),
+            (
+                source.x + insets.left,
+                source.w - insets.left - insets.right,
+                x + insets.left * scale.x,
+                width - (insets.left + insets.right) * scale.x,
+            ),
+            (
+                source.right() - insets.right,
+                insets.right,
+                x + width - insets.right * scale.x,
+                insets.right * scale.x,
+            ),
+        ];
+        let rows = [
+            (source.y, insets.top, y, insets.top * scale.y),
+            (
+                source.y + insets.top,
+                source.h - insets.top - insets.bottom,
+                y + insets.top * scale.y,
+                height - (insets.top + insets.bottom) * scale.y,
+            ),
+            (
+                source.bottom() - insets.bottom,
+                insets.bottom,
+                y + height - insets.bottom * scale.y,
+                insets.bottom * scale.y,
+            ),
+        ];
+
+        for (row, &(source_y, source_h, y, h)) in rows.iter().enumerate() {
+            for (column, &(source_x, source_w, x, w)) in columns.iter().enumerate() {
+                let mode = match (row, column) {
+                    (1, 1) => self.center,
+                    (1, _) | (_, 1) => self.edges,
+                    _ => SliceMode::Stretch,
+                };
+                let part = Rectangle::new(source_x, source_y, source_w, source_h);
+                self.draw_part(ctx, part, Rectangle::new(x, y, w, h), mode, color, blend);
+            }
+        }
+    }
+
+    fn draw_part(
+        &self,
+        ctx: &mut Context,
+        source: Rectangle,
+        dest: Rectangle,
+        mode: SliceMode,
+        color: Color,
+        blend: Option<BlendMode>,
+    ) {
+        if source.w <= 0.0 || source.h <= 0.0 || dest.w <= 0.0 || dest.h <= 0.0 {
+            return;
+        }
+
+        let (step_x, step_y) = match mode {
+            SliceMode::Stretch => (dest.w, dest.h),
+            SliceMode::Tile => (source.w, source.h),
+        };
+
+        let mut y = 0.0;
+        while y < dest.h {
+            let h = step_y.min(dest.h - y);
+            let mut x = 0.0;
+            while x < dest.w {
+                let w = step_x.min(dest.w - x);
+                let part = match mode {
+                    SliceMode::Stretch => source,
+                    SliceMode::Tile => Rectangle::new(source.x, source.y, w, h),
+                };
+                self.texture.draw(
+                    ctx,
+                    dest.x + x,
+                    dest.y + y,
+                    DrawParams {
+                        color,
+                        source: Some(part),
+                        dest_size: Some(vec2(w, h)),
+                        blend,
+                        ..Default::default()
+                    },
+                );
+                x += step_x;
+            }
+            y += step_y;
+        }
+    }
+}
+
+/// Draws a texture as a nine-slice filling the area,
+/// with the edges and center stretched or tiled by the mode.
+///
+/// See [NineSlice::draw] for how the parameters are used.
+pub fn draw_nine_slice(
+    ctx: &mut Context,
+    texture: &Texture,
+    insets: Insets,
+    mode: SliceMode,
+    area: Rectangle,
+    params: DrawParams,
+) {
+    NineSlice::new(texture.clone(), insets)
+        .with_mode(mode, mode)
+        .draw(ctx, area.x, area.y, area.w, area.h, params)
+}
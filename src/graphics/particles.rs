@@ -0,0 +1,579 @@
+use macroquad::prelude::rand::gen_range;
+
+use crate::{
+    context::Context,
+    error::ParseError,
+    math::{vec2, Vec2},
+};
+
+use super::{draw_circle, draw_rectangle, shapes::tint, Color, DrawParams, ShapeParams, Texture};
+
+/// Describes how an emitter creates particles and how they change over their lifetime.
+///
+/// Can be written to and read from a text file with [EmitterConfig::parse],
+/// so effects can be tweaked without recompiling.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EmitterConfig {
+    pub shape: EmitterShape,
+    /// Particles emitted per second while emitting.
+    pub rate: f32,
+    /// Particles emitted at once when the emitter starts.
+    pub burst: u32,
+    /// Most particles alive at once.
+    pub max_particles: usize,
+    /// Shortest and longest time a particle lives for, in seconds.
+    pub lifetime: (f32, f32),
+    /// Slowest and fastest starting speed, in pixels per second.
+    pub speed: (f32, f32),
+    /// Direction particles move in, in radians. Zero points right.
+    pub direction: f32,
+    /// Angle particles can move away from the direction, in radians.
+    pub spread: f32,
+    /// Acceleration of every particle, in pixels per second squared.
+    pub gravity: Vec2,
+    /// Size of a particle over its lifetime.
+    pub size: Curve<f32>,
+    /// Color of a particle over its lifetime.
+    pub color: Curve<Color>,
+    /// What particles are drawn as if the emitter has no texture.
+    pub particle: ParticleShape,
+}
+
+/// Area new particles are placed in, centered on the emitter.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EmitterShape {
+    Point,
+    Rectangle { width: f32, height: f32 },
+    Circle { radius: f32 },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParticleShape {
+    Circle,
+    Square,
+}
+
+/// Values at points of a particle's lifetime from 0 to 1, interpolated between.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Curve<T> {
+    keys: Vec<(f32, T)>,
+}
+
+/// Creates, moves and draws particles.
+#[derive(Debug, Clone)]
+pub struct ParticleEmitter {
+    pub config: EmitterConfig,
+    pub position: Vec2,
+    /// Texture drawn for each particle, scaled so its width is the particle's size.
+    pub texture: Option<Texture>,
+    particles: Vec<Particle>,
+    emitting: bool,
+    timer: f32,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Particle {
+    position: Vec2,
+    velocity: Vec2,
+    age: f32,
+    lifetime: f32,
+}
+
+impl Default for EmitterConfig {
+    fn default() -> Self {
+        Self {
+            shape: EmitterShape::Point,
+            rate: 10.0,
+            burst: 0,
+            max_particles: 1000,
+            lifetime: (1.0, 1.0),
+            speed: (50.0, 50.0),
+            direction: -std::f32::consts::FRAC_PI_2,
+            spread: 0.0,
+            gravity: Vec2::ZERO,
+            size: Curve::constant(4.0),
+            color: Curve::constant(Color::WHITE),
+            particle: ParticleShape::Square,
+        }
+    }
+}
+
+impl EmitterConfig {
+    /// Reads an emitter description written by [EmitterConfig::to_string].
+    ///
+    /// Each line holds a setting followed by its values, and settings left out are kept at
+    /// their defaults. Lines starting with `#` are comments.
+    /// Angles are in degrees and curves are written as `time:value` pairs.
+    ///
+    /// ```text
+    /// # sparks
+    /// shape circle 8
+    /// rate 30
+    /// burst 10
+    /// max 200
+    /// lifetime 0.5 1
+    /// speed 40 80
+    /// direction -90 30
+    /// gravity 0 98
+    /// size 0:4 1:0
+    /// color 0:yellow 0.5:#ff8000 1:#ff000000
+    /// particle circle
+    /// ```
+    pub fn parse(data: &str) -> Result<Self, ParseError> {
+        const FORMAT: &str = "emitter config";
+
+        let mut config = Self::default();
+
+        for (line, text) in data.lines().enumerate() {
+            let error = |message: &str| {
+                ParseError::new(FORMAT, format!("{} on line {}", message, line + 1))
+            };
+            let number = |value: Option<&str>| -> Result<f32, ParseError> {
+                value
+                    .and_then(|value| value.parse().ok())
+                    .ok_or_else(|| error("expected number"))
+            };
+
+            let mut values = text.split_whitespace();
+            let key = match values.next() {
+                Some(key) if !key.starts_with('#') => key,
+                _ => continue,
+            };
+
+            match key {
+                "shape" => {
+                    config.shape = match values.next() {
+                        Some("point") => EmitterShape::Point,
+                        Some("rectangle") => EmitterShape::Rectangle {
+                            width: number(values.next())?,
+                            height: number(values.next())?,
+                        },
+                        Some("circle") => EmitterShape::Circle {
+                            radius: number(values.next())?,
+                        },
+                        _ => return Err(error("unknown shape")),
+                    }
+                }
+                "rate" => config.rate = number(values.next())?,
+                "burst" => config.burst = number(values.next())? as _,
+                "max" => config.max_particles = number(values.next())? as _,
+                "lifetime" | "speed" => {
+                    let min = number(values.next())?;
+                    let range = (min, values.next().map_or(Ok(min), |max| number(Some(max)))?);
+                    match key {
+                        "lifetime" => config.lifetime = range,
+                        _ => config.speed = range,
+                    }
+                }
+                "direction" => {
+                    config.direction = number(values.next())?.to_radians();
+                    config.spread = values
+                        .next()
+                        .map_or(Ok(0.0), |spread| number(Some(spread)))?
+                        .to_radians();
+                }
+                "gravity" => config.gravity = vec2(number(values.next())?, number(values.next())?),
+                "size" | "color" => {
+                    let keys = values.map(|key| {
+                        key.split_once(':')
+                            .and_then(|(time, value)| Some((time.parse::<f32>().ok()?, value)))
+                            .ok_or_else(|| error("expected time:value"))
+                    });
+                    match key {
+                        "size" => {
+                            config.size = Curve::new(
+                                keys.map(|key| {
+                                    key.and_then(|(time, value)| Ok((time, number(Some(value))?)))
+                                })
+                                .collect::<Result<Vec<_>, _>>()?,
+                            )
+                        }
+                        _ => {
+                            config.color = Curve::new(
+                                keys.map(|key| {
+                                    key.and_then(|(time, value)| {
+                                        Ok((
+                                            time,
+                                            super::parse_color(value)
+                                                .ok_or_else(|| error("unknown color"))?,
+                                        ))
+                                    })
+                                })
+                                .collect::<Result<Vec<_>, _>>()?,
+                            )
+                        }
+                    }
+                }
+                "particle" => {
+                    config.particle = match values.next() {
+                        Some("circle") => ParticleShape::Circle,
+                        Some("square") => ParticleShape::Square,
+                        _ => return Err(error("unknown particle shape")),
+                    }
+                }
+                other => return Err(error(&format!("unknown setting \"{}\"", other))),
+            }
+        }
+
+        Ok(config)
+    }
+}
+
+impl std::fmt::Display for EmitterConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.shape {
+            EmitterShape::Point => writeln!(f, "shape point")?,
+            EmitterShape::Rectangle { width, height } => {
+                writeln!(f, "shape rectangle {} {}", width, height)?
+            }
+            EmitterShape::Circle { radius } => writeln!(f, "shape circle {}", radius)?,
+        }
+        writeln!(f, "rate {}", self.rate)?;
+        writeln!(f, "burst {}", self.burst)?;
+        writeln!(f, "max {}", self.max_particles)?;
+        writeln!(f, "lifetime {} {}", self.lifetime.0, self.lifetime.1)?;
+        writeln!(f, "speed {} {}", self.speed.0, self.speed.1)?;
+        writeln!(
+            f,
+            "direction {} {}",
+            self.direction.to_degrees(),
+            self.spread.to_degrees()
+        )?;
+        writeln!(f, "gravity {} {}", self.gravity.x, self.gravity.y)?;
+        write!(f, "size")?;
+        for (time, size) in &self.size.keys {
+            write!(f, " {}:{}", time, size)?;
+        }
+        write!(f, "\ncolor")?;
+        for (time, color) in &self.color.keys {
+            let [r, g, b, a]: [u8; 4] = (*color).into();
+            write!(f, " {}:#{:02x}{:02x}{:02x}{:02x}", time, r, g, b, a)?;
+        }
+        writeln!(f)?;
+        match self.particle {
+            ParticleShape::Circle => writeln!(f, "particle circle"),
+            ParticleShape::Square => writeln!(f, "particle square"),
+        }
+    }
+}
+
+impl<T: Copy> Curve<T> {
+    /// Creates a curve from keys, which are sorted by their time.
+    pub fn new(keys: impl IntoIterator<Item = (f32, T)>) -> Self {
+        let mut keys = keys.into_iter().collect::<Vec<_>>();
+        keys.sort_by(|a, b| a.0.total_cmp(&b.0));
+        Self { keys }
+    }
+
+    /// A curve with the same value over the whole lifetime.
+    pub fn constant(value: T) -> Self {
+        Self {
+            keys: vec![(0.0, value)],
+        }
+    }
+
+    pub fn keys(&self) -> &[(f32, T)] {
+        &self.keys
+    }
+
+    fn sample_with(&self, time: f32, lerp: impl Fn(T, T, f32) -> T) -> Option<T> {
+        let next = self.keys.iter().position(|(key, ..)| *key > time);
+        match next {
+            Some(0) => self.keys.first().map(|(.., value)| *value),
+            Some(next) => {
+                let (start, from) = self.keys[next - 1];
+                let (end, to) = self.keys[next];
+                Some(lerp(from, to, (time - start) / (end - start)))
+            }
+            None => self.keys.last().map(|(.., value)| *value),
+        }
+    }
+}
+
+impl Curve<f32> {
+    /// The value at a time from 0 to 1.
+    pub fn sample(&self, time: f32) -> f32 {
+        self.sample_with(time, |from, to, amount| from + (to - from) * amount)
+            .unwrap_or_default()
+    }
+}
+
+impl Curve<Color> {
+    /// The color at a time from 0 to 1.
+    pub fn sample(&self, time: f32) -> Color {
+        self.sample_with(time, |from, to, amount| {
+            Color::new(
+                from.r + (to.r - from.r) * amount,
+                from.g + (to.g - from.g) * amount,
+                from.b + (to.b - from.b) * amount,
+                from.a + (to.a - from.a) * amount,
+            )
+        })
+        .unwrap_or(Color::WHITE)
+    }
+}
+
+impl ParticleEmitter {
+    /// Creates an emitter that starts emitting straight away.
+    pub fn new(config: EmitterConfig, position: Vec2) -> Self {
+        let mut emitter = Self {
+            config,
+            position,
+            texture: None,
+            particles: Vec::new(),
+            emitting: false,
+            timer: 0.0,
+        };
+        emitter.start();
+        emitter
+    }
+
+    pub fn with_texture(mut self, texture: Texture) -> Self {
+        self.texture = Some(texture);
+        self
+    }
+
+    /// Starts emitting at the configured rate, emitting the configured burst first.
+    pub fn start(&mut self) {
+        self.emitting = true;
+        self.timer = 0.0;
+        self.burst(self.config.burst);
+    }
+
+    /// Stops emitting new particles. Particles already emitted live out their lifetime.
+    pub fn stop(&mut self) {
+        self.emitting = false;
+    }
+
+    pub fn is_emitting(&self) -> bool {
+        self.emitting
+    }
+
+    /// Emits a number of particles at once.
+    pub fn burst(&mut self, count: u32) {
+        for _ in 0..count {
+            self.emit();
+        }
+    }
+
+    /// Removes every particle.
+    pub fn clear(&mut self) {
+        self.particles.clear();
+    }
+
+    /// Number of particles alive.
+    pub fn len(&self) -> usize {
+        self.particles.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.particles.is_empty()
+    }
+
+    /// Whether the emitter has stopped and every particle has died.
+    pub fn finished(&self) -> bool {
+        !self.emitting && self.particles.is_empty()
+    }
+
+    pub fn update(&mut self, delta: f32) {
+        let gravity = self.config.gravity;
+        self.particles.retain_mut(|particle| {
+            particle.age += delta;
+            particle.velocity += gravity * delta;
+            particle.position += particle.velocity * delta;
+            particle.age < particle.lifetime
+        });
+
+        if self.emitting && self.config.rate > 0.0 {
+            self.timer += delta;
+            let step = 1.0 / self.config.rate;
+            while self.timer >= step {
+                self.timer -= step;
+                self.emit();
+            }
+        }
+    }
+
+    /// Draws every particle. The color of the parameters tints the particles,
+    /// and all of them are drawn with the same blend mode and draw order.
+    pub fn draw(&self, ctx: &mut Context, params: impl Into<ShapeParams>) {
+        let params = params.into();
+        for particle in &self.particles {
+            let time = particle.age / particle.lifetime;
+            let size = self.config.size.sample(time);
+            let color = tint(self.config.color.sample(time), params.color);
+            let position = particle.position;
+            let shape = ShapeParams { color, ..params };
+
+            match &self.texture {
+                Some(texture) => {
+                    let size = vec2(size, size * texture.height() / texture.width());
+                    texture.draw(
+                        ctx,
+                        position.x - size.x / 2.0,
+                        position.y - size.y / 2.0,
+                        DrawParams {
+                            color,
+                            dest_size: Some(size),
+                            blend: params.blend,
+                            order: params.order,
+                            ..Default::default()
+                        },
+                    );
+                }
+                None => match self.config.particle {
+                    ParticleShape::Circle => {
+                        draw_circle(ctx, position.x, position.y, size / 2.0, shape)
+                    }
+                    ParticleShape::Square => draw_rectangle(
+                        ctx,
+                        position.x - size / 2.0,
+                        position.y - size / 2.0,
+                        size,
+                        size,
+                        shape,
+                    ),
+                },
+            }
+        }
+    }
+
+    fn emit(&mut self) {
+        if self.particles.len() >= self.config.max_particles {
+            return;
+        }
+
+        let config = &self.config;
+        let range = |(min, max): (f32, f32)| match min < max {
+            true => gen_range(min, max),
+            false => min,
+        };
+
+        let offset = match config.shape {
+            EmitterShape::Point => Vec2::ZERO,
+            EmitterShape::Rectangle { width, height } => vec2(
+                range((-width / 2.0, width / 2.0)),
+                range((-height / 2.0, height / 2.0)),
+            ),
+            EmitterShape::Circle { radius } => {
+                let angle = range((0.0, std::f32::consts::TAU));
+                let distance = radius * range((0.0, 1.0)).sqrt();
+                vec2(angle.cos(), angle.sin()) * distance
+            }
+        };
+
+        let angle = config.direction + range((-config.spread, config.spread));
+        let speed = range(config.speed);
+
+        self.particles.push(Particle {
+            position: self.position + offset,
+            velocity: vec2(angle.cos(), angle.sin()) * speed,
+            age: 0.0,
+            lifetime: range(config.lifetime).max(f32::EPSILON),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let config = EmitterConfig {
+            shape: EmitterShape::Rectangle {
+                width: 16.0,
+                height: 4.5,
+            },
+            rate: 30.0,
+            burst: 10,
+            max_particles: 200,
+            lifetime: (0.5, 1.0),
+            speed: (40.0, 80.0),
+            direction: -90f32.to_radians(),
+            spread: 30f32.to_radians(),
+            gravity: vec2(0.0, 98.0),
+            size: Curve::new([(0.0, 4.0), (1.0, 0.0)]),
+            color: Curve::new([
+                (0.0, Color::from_rgba(255, 255, 0, 255)),
+                (0.5, Color::from_rgba(255, 128, 0, 255)),
+                (1.0, Color::from_rgba(255, 0, 0, 0)),
+            ]),
+            particle: ParticleShape::Circle,
+        };
+        let parsed = EmitterConfig::parse(&config.to_string()).unwrap();
+        assert_eq!(parsed.to_string(), config.to_string());
+        assert_eq!(parsed.shape, config.shape);
+        assert_eq!(parsed.size, config.size);
+        assert_eq!(parsed.color, config.color);
+        assert!((parsed.direction - config.direction).abs() < 1e-6);
+        assert!((parsed.spread - config.spread).abs() < 1e-6);
+
+        for shape in [EmitterShape::Point, EmitterShape::Circle { radius: 8.0 }] {
+            let config = EmitterConfig {
+                shape,
+                ..Default::default()
+            };
+            assert_eq!(
+                EmitterConfig::parse(&config.to_string()).unwrap().shape,
+                shape
+            );
+        }
+    }
+
+    #[test]
+    fn parse() {
+        let config = EmitterConfig::parse(
+            "# sparks\n\nshape circle 8\nlifetime 2\ndirection 180\ncolor 0:yellow 1:#ff000000\n",
+        )
+        .unwrap();
+        assert_eq!(config.shape, EmitterShape::Circle { radius: 8.0 });
+        assert_eq!(config.lifetime, (2.0, 2.0));
+        assert_eq!(config.direction, std::f32::consts::PI);
+        assert_eq!(config.spread, 0.0);
+        assert_eq!(config.color.keys()[0], (0.0, Color::YELLOW));
+        assert_eq!(config.rate, EmitterConfig::default().rate);
+    }
+
+    #[test]
+    fn parse_rejects() {
+        for data in [
+            "shape",
+            "shape triangle",
+            "shape circle",
+            "rate fast",
+            "gravity 1",
+            "size 4",
+            "size 0:big",
+            "color 0:nope",
+            "particle star",
+            "speed",
+            "wind 1 2",
+        ] {
+            assert!(EmitterConfig::parse(data).is_err(), "{}", data);
+        }
+    }
+
+    #[test]
+    fn sample() {
+        let curve = Curve::new([(1.0, 0.0), (0.0, 4.0), (0.5, 2.0)]);
+        assert_eq!(curve.keys()[0], (0.0, 4.0));
+        assert_eq!(curve.sample(-1.0), 4.0);
+        assert_eq!(curve.sample(0.0), 4.0);
+        assert_eq!(curve.sample(0.25), 3.0);
+        assert_eq!(curve.sample(0.5), 2.0);
+        assert_eq!(curve.sample(0.75), 1.0);
+        assert_eq!(curve.sample(2.0), 0.0);
+
+        assert_eq!(Curve::constant(3.0).sample(0.7), 3.0);
+        assert_eq!(Curve::<f32>::new([]).sample(0.5), 0.0);
+
+        let colors = Curve::new([
+            (0.0, Color::new(0.0, 0.0, 0.0, 0.0)),
+            (1.0, Color::new(1.0, 0.5, 0.0, 1.0)),
+        ]);
+        assert_eq!(colors.sample(0.5), Color::new(0.5, 0.25, 0.0, 0.5));
+        assert_eq!(Curve::<Color>::new([]).sample(0.5), Color::WHITE);
+    }
+}
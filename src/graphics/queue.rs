@@ -0,0 +1,157 @@
+use macroquad::prelude::Vertex;
+
+use crate::context::Context;
+
+use super::{BlendMode, DrawParams, Texture};
+
+/// Where a deferred draw call is placed among the others.
+///
+/// Lower layers are drawn first, and within a layer lower keys are drawn first.
+/// Draw calls with the same layer and key are drawn in the order they were made.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct DrawOrder {
+    pub layer: i32,
+
+    /// Sorts draw calls within a layer, such as the y coordinate of a sprite's feet.
+    pub key: f32,
+}
+
+impl DrawOrder {
+    pub const fn new(layer: i32, key: f32) -> Self {
+        Self { layer, key }
+    }
+
+    pub const fn layer(layer: i32) -> Self {
+        Self::new(layer, 0.0)
+    }
+}
+
+#[derive(Default)]
+pub(crate) struct DrawQueue(Vec<(DrawOrder, DrawCommand)>);
+
+enum DrawCommand {
+    Texture(Texture, f32, f32, DrawParams),
+    Geometry(Vec<Vertex>, Vec<u32>, Option<BlendMode>),
+    Custom(Box<dyn FnOnce(&mut Context)>),
+}
+
+impl DrawQueue {
+    pub(crate) fn push_texture(
+        &mut self,
+        order: DrawOrder,
+        texture: &Texture,
+        x: f32,
+        y: f32,
+        params: DrawParams,
+    ) {
+        let params = DrawParams {
+            order: None,
+            ..params
+        };
+        self.0
+            .push((order, DrawCommand::Texture(texture.clone(), x, y, params)));
+    }
+
+    /// Queues untextured triangles.
+    pub(crate) fn push_geometry(
+        &mut self,
+        order: DrawOrder,
+        vertices: &[Vertex],
+        indices: &[u32],
+        blend: Option<BlendMode>,
+    ) {
+        self.0.push((
+            order,
+            DrawCommand::Geometry(vertices.to_vec(), indices.to_vec(), blend),
+        ));
+    }
+
+    /// Empties the queue, returning its commands in the order they are drawn.
+    fn take_sorted(&mut self) -> Vec<(DrawOrder, DrawCommand)> {
+        let mut commands = std::mem::take(&mut self.0);
+        // a stable sort, so ties are drawn in the order they were queued
+        commands
+            .sort_by(|(a, _), (b, _)| a.layer.cmp(&b.layer).then_with(|| a.key.total_cmp(&b.key)));
+        commands
+    }
+}
+
+/// Defers the draw calls made by the function until the draw queue is flushed.
+///
+/// Useful for text and other draw calls without a draw order of their own.
+pub fn draw_deferred(
+    ctx: &mut Context,
+    order: DrawOrder,
+    draw: impl FnOnce(&mut Context) + 'static,
+) {
+    ctx.draw_queue
+        .0
+        .push((order, DrawCommand::Custom(Box::new(draw))));
+}
+
+/// Draws everything in the draw queue, sorted by its [DrawOrder].
+///
+/// Queued draw calls use the state they are flushed in, so the queue is flushed
+/// after [State::draw](crate::State::draw) and before anything they are drawn with changes:
+/// the canvas, the camera, the shader, the default blend mode and the contents of textures.
+/// Consecutive draws of the same texture are still batched together.
+///
+/// Draw calls queued before a flush are never sorted with ones queued after it,
+/// so changing any of that state in the middle of drawing splits the ordering in two.
+pub fn flush_draw_queue(ctx: &mut Context) {
+    // draw calls queued while flushing are drawn in the next pass
+    while !ctx.draw_queue.0.is_empty() {
+        for (_, command) in ctx.draw_queue.take_sorted() {
+            match command {
+                DrawCommand::Texture(texture, x, y, params) => texture.draw(ctx, x, y, params),
+                DrawCommand::Geometry(vertices, indices, blend) => {
+                    super::draw_geometry(ctx, None, blend, &vertices, &indices)
+                }
+                DrawCommand::Custom(draw) => draw(ctx),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn push(queue: &mut DrawQueue, layer: i32, key: f32, marker: u32) {
+        queue.push_geometry(DrawOrder::new(layer, key), &[], &[marker], None);
+    }
+
+    fn markers(queue: &mut DrawQueue) -> Vec<u32> {
+        queue
+            .take_sorted()
+            .into_iter()
+            .map(|(_, command)| match command {
+                DrawCommand::Geometry(_, indices, _) => indices[0],
+                _ => unreachable!(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn sorts_by_layer_then_key() {
+        let mut queue = DrawQueue::default();
+        push(&mut queue, 1, 0.0, 0);
+        push(&mut queue, 0, 5.0, 1);
+        push(&mut queue, 0, -2.0, 2);
+        push(&mut queue, -1, 10.0, 3);
+        push(&mut queue, 1, -1.0, 4);
+        assert_eq!(markers(&mut queue), [3, 2, 1, 4, 0]);
+        assert!(queue.0.is_empty());
+    }
+
+    #[test]
+    fn keeps_queued_order_for_ties() {
+        let mut queue = DrawQueue::default();
+        for marker in 0..4 {
+            push(&mut queue, 0, 1.0, marker);
+            push(&mut queue, 0, 0.0, marker + 10);
+        }
+        assert_eq!(markers(&mut queue), [10, 11, 12, 13, 0, 1, 2, 3]);
+    }
+}
@@ -0,0 +1,79 @@
+use macroquad::{
+    miniquad::{self, TextureParams},
+    prelude::{get_internal_gl, screen_height, screen_width, Texture2D},
+};
+
+use crate::{context::Context, error::EngineError, input::keyboard::Key};
+
+use super::{image::dimension_mismatch, Canvas, Image, ImageFormat};
+
+/// Captures everything drawn to the window so far this frame.
+#[allow(unused_variables)]
+pub fn screenshot(ctx: &mut Context) -> Result<Image, EngineError> {
+    let mut gl = unsafe { get_internal_gl() };
+    gl.flush();
+
+    let texture = miniquad::Texture::new_render_texture(
+        gl.quad_context,
+        TextureParams {
+            width: screen_width() as _,
+            height: screen_height() as _,
+            ..Default::default()
+        },
+    );
+    let texture = Texture2D::from_miniquad_texture(texture);
+    texture.grab_screen();
+    let image = flipped(texture.get_texture_data());
+    texture.delete();
+    image
+}
+
+impl Canvas {
+    /// Copies what has been drawn to the canvas into an image.
+    pub fn to_image(&self) -> Result<Image, EngineError> {
+        unsafe { get_internal_gl() }.flush();
+        // canvases are drawn with a flipped camera, so their rows are already top down
+        rgba(self.texture().data().get_texture_data())
+    }
+}
+
+/// The window is read from the bottom row up.
+fn flipped(image: macroquad::prelude::Image) -> Result<Image, EngineError> {
+    Ok(Image(image::imageops::flip_vertical(&rgba(image)?.0)))
+}
+
+fn rgba(image: macroquad::prelude::Image) -> Result<Image, EngineError> {
+    image::RgbaImage::from_raw(image.width as _, image.height as _, image.bytes)
+        .map(Image)
+        .ok_or_else(dimension_mismatch)
+}
+
+/// Captures everything drawn to the window so far this frame as a PNG file.
+pub fn screenshot_png(ctx: &mut Context) -> Result<Vec<u8>, EngineError> {
+    Ok(screenshot(ctx)?.encode(ImageFormat::Png)?)
+}
+
+/// Sets the key that takes a screenshot at the end of the frame it is pressed in.
+/// Screenshots are collected with [take_screenshot].
+pub fn set_screenshot_key(ctx: &mut Context, key: Option<Key>) {
+    ctx.screenshot_key = key;
+}
+
+pub fn screenshot_key(ctx: &Context) -> Option<Key> {
+    ctx.screenshot_key
+}
+
+/// Takes the last screenshot made with the [screenshot key](set_screenshot_key), if there is one.
+/// Encode it with [Image::encode] or [Image::save] to get a PNG file.
+pub fn take_screenshot(ctx: &mut Context) -> Option<Result<Image, EngineError>> {
+    ctx.screenshot.take()
+}
+
+/// Captures the frame if the screenshot key was pressed.
+pub(crate) fn update_screenshot(ctx: &mut Context) {
+    if let Some(key) = ctx.screenshot_key {
+        if crate::input::keyboard::pressed(ctx, key) {
+            ctx.screenshot = Some(screenshot(ctx));
+        }
+    }
+}
@@ -0,0 +1,254 @@
+use macroquad::prelude::{
+    gl_use_default_material, gl_use_material, load_material, Material, MaterialParams,
+};
+use std::{cell::RefCell, rc::Rc};
+
+pub use macroquad::prelude::UniformType;
+
+use crate::{context::Context, error::EngineError, utils::HashMap};
+
+use super::{BlendMode, Canvas, DrawParams, Texture};
+
+/// Vertex shader used by [Shader::fragment], passing on the texture coordinates as `uv`
+/// and the draw color as `color`.
+pub const DEFAULT_VERTEX_SHADER: &str = r#"#version 100
+attribute vec3 position;
+attribute vec2 texcoord;
+attribute vec4 color0;
+
+varying lowp vec2 uv;
+varying lowp vec4 color;
+
+uniform mat4 Model;
+uniform mat4 Projection;
+
+void main() {
+    gl_Position = Projection * Model * vec4(position, 1);
+    color = color0 / 255.0;
+    uv = texcoord;
+}"#;
+
+/// A GLSL program used for drawing instead of the default one.
+///
+/// The texture being drawn is bound to `uniform sampler2D Texture`.
+#[derive(Debug, Clone)]
+pub struct Shader(Rc<ShaderData>);
+
+#[derive(Debug)]
+pub struct ShaderData {
+    material: Material,
+    /// Textures bound to the shader, kept alive while they are in use.
+    textures: RefCell<HashMap<String, Texture>>,
+}
+
+/// Applies a chain of shaders to everything drawn to a canvas.
+///
+/// Each shader reads the result of the one before it from its `Texture`.
+#[derive(Debug, Clone)]
+pub struct PostProcess {
+    shaders: Vec<Shader>,
+    canvases: [Canvas; 2],
+}
+
+impl Shader {
+    /// Compiles a shader, declaring the uniforms and extra textures it uses.
+    /// Shaders draw with [BlendMode::Alpha].
+    pub fn new(
+        ctx: &mut Context,
+        vertex: &str,
+        fragment: &str,
+        uniforms: &[(&str, UniformType)],
+        textures: &[&str],
+    ) -> Result<Self, EngineError> {
+        Self::with_blend_mode(ctx, vertex, fragment, uniforms, textures, BlendMode::Alpha)
+    }
+
+    /// Compiles a shader that draws with the given blend mode.
+    /// The blend mode of draw calls made while the shader is in use is ignored.
+    #[allow(unused_variables)]
+    pub fn with_blend_mode(
+        ctx: &mut Context,
+        vertex: &str,
+        fragment: &str,
+        uniforms: &[(&str, UniformType)],
+        textures: &[&str],
+        blend: BlendMode,
+    ) -> Result<Self, EngineError> {
+        let material = load_material(
+            vertex,
+            fragment,
+            MaterialParams {
+                pipeline_params: blend.pipeline_params(),
+                uniforms: uniforms
+                    .iter()
+                    .map(|(name, kind)| (name.to_string(), *kind))
+                    .collect(),
+                textures: textures.iter().map(|name| name.to_string()).collect(),
+            },
+        )
+        .map_err(EngineError::Shader)?;
+        Ok(Self(Rc::new(ShaderData {
+            material,
+            textures: Default::default(),
+        })))
+    }
+
+    /// Compiles a fragment shader used with the [DEFAULT_VERTEX_SHADER].
+    pub fn fragment(
+        ctx: &mut Context,
+        fragment: &str,
+        uniforms: &[(&str, UniformType)],
+        textures: &[&str],
+    ) -> Result<Self, EngineError> {
+        Self::new(ctx, DEFAULT_VERTEX_SHADER, fragment, uniforms, textures)
+    }
+
+    /// Sets the value of a uniform declared when the shader was created.
+    /// Values are set for every following draw call using the shader.
+    pub fn set_uniform<T>(&self, name: &str, value: T) {
+        self.0.material.set_uniform(name, value)
+    }
+
+    /// Binds a texture declared when the shader was created.
+    pub fn set_texture(&self, name: &str, texture: &Texture) {
+        self.0.material.set_texture(name, **texture.data());
+        self.0
+            .textures
+            .borrow_mut()
+            .insert(name.to_owned(), texture.clone());
+    }
+
+    pub(crate) fn apply(&self) {
+        gl_use_material(self.0.material);
+    }
+}
+
+impl PartialEq for Shader {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Drop for ShaderData {
+    fn drop(&mut self) {
+        self.material.delete()
+    }
+}
+
+/// Uses the shader for the following draw calls.
+///
+/// Flushes the [draw queue](super::flush_draw_queue), so draw calls queued before this
+/// are not sorted with ones queued after it.
+pub fn set_shader(ctx: &mut Context, shader: &Shader) {
+    super::flush_draw_queue(ctx);
+    ctx.blending.shader = true;
+    shader.apply()
+}
+
+/// Goes back to drawing without a shader.
+///
+/// Flushes the [draw queue](super::flush_draw_queue), so draw calls queued before this
+/// are not sorted with ones queued after it.
+pub fn reset_shader(ctx: &mut Context) {
+    super::flush_draw_queue(ctx);
+    ctx.blending.shader = false;
+    ctx.blending.reset();
+    gl_use_default_material()
+}
+
+impl Texture {
+    /// Draws the texture with a shader.
+    ///
+    /// With a draw order, setting the shader, drawing and going back to the default shader
+    /// are queued together, so the shader is only used for this texture.
+    pub fn draw_with_shader(
+        &self,
+        ctx: &mut Context,
+        shader: &Shader,
+        x: f32,
+        y: f32,
+        params: DrawParams,
+    ) {
+        if let Some(order) = params.order {
+            let (texture, shader) = (self.clone(), shader.clone());
+            let params = DrawParams {
+                order: None,
+                ..params
+            };
+            return super::draw_deferred(ctx, order, move |ctx| {
+                texture.draw_with_shader(ctx, &shader, x, y, params)
+            });
+        }
+
+        set_shader(ctx, shader);
+        self.draw(ctx, x, y, params);
+        reset_shader(ctx);
+    }
+}
+
+impl PostProcess {
+    /// Creates a post-processing chain for scenes of the given size.
+    pub fn new(ctx: &mut Context, width: u32, height: u32) -> Self {
+        Self {
+            shaders: Vec::new(),
+            canvases: [
+                Canvas::new(ctx, width, height),
+                Canvas::new(ctx, width, height),
+            ],
+        }
+    }
+
+    pub fn add(&mut self, shader: Shader) {
+        self.shaders.push(shader);
+    }
+
+    pub fn with(mut self, shader: Shader) -> Self {
+        self.add(shader);
+        self
+    }
+
+    pub fn shaders(&self) -> &[Shader] {
+        &self.shaders
+    }
+
+    pub fn shaders_mut(&mut self) -> &mut Vec<Shader> {
+        &mut self.shaders
+    }
+
+    /// The canvas the scene is drawn to between [PostProcess::begin] and [PostProcess::end].
+    pub fn canvas(&self) -> &Canvas {
+        &self.canvases[0]
+    }
+
+    /// Starts drawing the scene to be processed.
+    pub fn begin(&self, ctx: &mut Context) {
+        super::set_canvas(ctx, &self.canvases[0]);
+    }
+
+    /// Runs each shader over the scene, drawing the result with the last one.
+    ///
+    /// With a draw order, only drawing the result is queued.
+    /// The scene's canvas should not be drawn to again until the queue is flushed.
+    pub fn end(&self, ctx: &mut Context, x: f32, y: f32, params: DrawParams) {
+        let mut source = 0;
+
+        if let Some((last, passes)) = self.shaders.split_last() {
+            for shader in passes {
+                let target = 1 - source;
+                super::set_canvas(ctx, &self.canvases[target]);
+                super::clear(ctx, super::Color::new(0.0, 0.0, 0.0, 0.0));
+                set_shader(ctx, shader);
+                self.canvases[source].draw(ctx, 0.0, 0.0, Default::default());
+                reset_shader(ctx);
+                source = target;
+            }
+            super::reset_canvas(ctx);
+            self.canvases[source]
+                .texture()
+                .draw_with_shader(ctx, last, x, y, params);
+        } else {
+            super::reset_canvas(ctx);
+            self.canvases[source].draw(ctx, x, y, params);
+        }
+    }
+}
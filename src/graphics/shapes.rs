@@ -1,16 +1,37 @@
-use super::{Color, Context};
+use macroquad::prelude::Vertex;
+use std::f32::consts::{FRAC_PI_2, PI, TAU};
+
+use crate::math::{vec2, Vec2};
+
+use super::{BlendMode, Color, ColorExt, Context, DrawOrder};
 
 #[allow(unused_variables)]
 pub fn clear(ctx: &mut Context, color: Color) {
     macroquad::prelude::clear_background(color);
 }
 
-#[allow(unused_variables)]
-pub fn draw_rectangle(ctx: &mut Context, x: f32, y: f32, w: f32, h: f32, color: Color) {
-    macroquad::prelude::draw_rectangle(x, y, w, h, color)
+pub fn draw_rectangle(
+    ctx: &mut Context,
+    x: f32,
+    y: f32,
+    w: f32,
+    h: f32,
+    params: impl Into<ShapeParams>,
+) {
+    let params = params.into();
+    draw_triangles(
+        ctx,
+        params,
+        &[
+            vertex(vec2(x, y), params.color),
+            vertex(vec2(x + w, y), params.color),
+            vertex(vec2(x + w, y + h), params.color),
+            vertex(vec2(x, y + h), params.color),
+        ],
+        &[0, 1, 2, 0, 2, 3],
+    )
 }
 
-#[allow(unused_variables)]
 pub fn draw_rectangle_lines(
     ctx: &mut Context,
     x: f32,
@@ -18,9 +39,30 @@ pub fn draw_rectangle_lines(
     w: f32,
     h: f32,
     thickness: f32,
-    color: Color,
+    params: impl Into<ShapeParams>,
 ) {
-    macroquad::prelude::draw_rectangle_lines(x, y, w, h, thickness, color)
+    let params = params.into();
+    let t = thickness / 2.0;
+    let vertices = [
+        vec2(x, y),
+        vec2(x + w, y),
+        vec2(x + w, y + h),
+        vec2(x, y + h),
+        // inner rectangle
+        vec2(x + t, y + t),
+        vec2(x + w - t, y + t),
+        vec2(x + w - t, y + h - t),
+        vec2(x + t, y + h - t),
+    ]
+    .map(|position| vertex(position, params.color));
+    draw_triangles(
+        ctx,
+        params,
+        &vertices,
+        &[
+            0, 1, 4, 1, 4, 5, 1, 5, 6, 1, 2, 6, 3, 7, 2, 2, 7, 6, 0, 4, 3, 3, 4, 7,
+        ],
+    )
 }
 
 /// Deprecated
@@ -31,28 +73,834 @@ pub fn draw_straight_line(
     len: f32,
     horizontal: bool,
     thickness: f32,
-    color: Color,
+    params: impl Into<ShapeParams>,
 ) {
     match horizontal {
-        true => draw_line(ctx, x, x, x + len, y, thickness, color),
-        false => draw_line(ctx, x, y, x, y + len, thickness, color),
+        true => draw_line(ctx, x, x, x + len, y, thickness, params),
+        false => draw_line(ctx, x, y, x, y + len, thickness, params),
     }
 }
 
 pub fn draw_line(
-    _: &mut Context,
+    ctx: &mut Context,
     x1: f32,
     y1: f32,
     x2: f32,
     y2: f32,
     thickness: f32,
-    color: Color,
+    params: impl Into<ShapeParams>,
 ) {
-    macroquad::prelude::draw_line(x1, y1, x2, y2, thickness, color)
+    let params = params.into();
+    let (from, to) = (vec2(x1, y1), vec2(x2, y2));
+    let offset = (to - from).perp().normalize_or_zero() * thickness / 2.0;
+    if offset == Vec2::ZERO {
+        return;
+    }
+    draw_triangles(
+        ctx,
+        params,
+        &[
+            vertex(from + offset, params.color),
+            vertex(from - offset, params.color),
+            vertex(to + offset, params.color),
+            vertex(to - offset, params.color),
+        ],
+        &[0, 1, 2, 2, 1, 3],
+    )
 }
 
-#[allow(unused_variables)]
-pub fn draw_circle(_: &mut Context, x: f32, y: f32, r: f32, color: Color) {
-    // todo!("draw circle")
-    macroquad::prelude::draw_circle(x, y, r, color);
+pub fn draw_circle(ctx: &mut Context, x: f32, y: f32, r: f32, params: impl Into<ShapeParams>) {
+    draw_sector_ellipse(ctx, vec2(x, y), r, r, 0.0, TAU, params.into())
+}
+
+pub fn draw_circle_lines(
+    ctx: &mut Context,
+    x: f32,
+    y: f32,
+    r: f32,
+    thickness: f32,
+    params: impl Into<ShapeParams>,
+) {
+    draw_ellipse_lines(ctx, x, y, r, r, thickness, params)
+}
+
+/// Draws a circle fading from the inner color at its center to the outer color at its edge.
+/// The color of the parameters tints both colors.
+pub fn draw_circle_gradient(
+    ctx: &mut Context,
+    x: f32,
+    y: f32,
+    r: f32,
+    inner: Color,
+    outer: Color,
+    params: impl Into<ShapeParams>,
+) {
+    let params = params.into();
+    let center = vec2(x, y);
+    let mut vertices = vec![vertex(center, tint(inner, params.color))];
+    vertices.extend(
+        ellipse_points(center, r, r, 0.0, TAU, segments(r))
+            .map(|point| vertex(point, tint(outer, params.color))),
+    );
+    draw_triangles(ctx, params, &vertices, &fan(vertices.len() - 1));
+}
+
+pub fn draw_ellipse(
+    ctx: &mut Context,
+    x: f32,
+    y: f32,
+    rx: f32,
+    ry: f32,
+    params: impl Into<ShapeParams>,
+) {
+    draw_sector_ellipse(ctx, vec2(x, y), rx, ry, 0.0, TAU, params.into())
+}
+
+pub fn draw_ellipse_lines(
+    ctx: &mut Context,
+    x: f32,
+    y: f32,
+    rx: f32,
+    ry: f32,
+    thickness: f32,
+    params: impl Into<ShapeParams>,
+) {
+    draw_ring(ctx, vec2(x, y), rx, ry, 0.0, TAU, thickness, params.into())
+}
+
+/// Draws the outline of part of a circle, going clockwise from the start to the end angle in radians.
+#[allow(clippy::too_many_arguments)]
+pub fn draw_arc(
+    ctx: &mut Context,
+    x: f32,
+    y: f32,
+    r: f32,
+    start: f32,
+    end: f32,
+    thickness: f32,
+    params: impl Into<ShapeParams>,
+) {
+    draw_ring(ctx, vec2(x, y), r, r, start, end, thickness, params.into())
+}
+
+/// Draws a filled slice of a circle, going clockwise from the start to the end angle in radians.
+pub fn draw_sector(
+    ctx: &mut Context,
+    x: f32,
+    y: f32,
+    r: f32,
+    start: f32,
+    end: f32,
+    params: impl Into<ShapeParams>,
+) {
+    draw_sector_ellipse(ctx, vec2(x, y), r, r, start, end, params.into())
+}
+
+pub fn draw_triangle(ctx: &mut Context, a: Vec2, b: Vec2, c: Vec2, params: impl Into<ShapeParams>) {
+    let params = params.into();
+    draw_triangles(
+        ctx,
+        params,
+        &[
+            vertex(a, params.color),
+            vertex(b, params.color),
+            vertex(c, params.color),
+        ],
+        &[0, 1, 2],
+    )
+}
+
+pub fn draw_triangle_lines(
+    ctx: &mut Context,
+    a: Vec2,
+    b: Vec2,
+    c: Vec2,
+    thickness: f32,
+    params: impl Into<ShapeParams>,
+) {
+    draw_polygon_lines(ctx, &[a, b, c], thickness, params)
+}
+
+/// Draws a filled polygon. The polygon can be concave but its edges should not cross.
+pub fn draw_polygon(ctx: &mut Context, points: &[Vec2], params: impl Into<ShapeParams>) {
+    let params = params.into();
+    let vertices = points
+        .iter()
+        .map(|point| vertex(*point, params.color))
+        .collect::<Vec<_>>();
+    draw_triangles(ctx, params, &vertices, &triangulate(points));
+}
+
+/// Draws a filled polygon with a gradient stretched over its bounds.
+/// The color of the parameters tints the gradient.
+pub fn draw_polygon_gradient(
+    ctx: &mut Context,
+    points: &[Vec2],
+    gradient: Gradient,
+    params: impl Into<ShapeParams>,
+) {
+    let params = params.into();
+    let (min, max) = points.iter().fold(
+        (Vec2::splat(f32::INFINITY), Vec2::splat(f32::NEG_INFINITY)),
+        |(min, max), point| (min.min(*point), max.max(*point)),
+    );
+    let size = (max - min).max(Vec2::splat(f32::EPSILON));
+    let vertices = points
+        .iter()
+        .map(|point| {
+            let position = (*point - min) / size;
+            let color = gradient.sample(position.x, position.y);
+            vertex(*point, tint(color, params.color))
+        })
+        .collect::<Vec<_>>();
+    draw_triangles(ctx, params, &vertices, &triangulate(points));
+}
+
+pub fn draw_polygon_lines(
+    ctx: &mut Context,
+    points: &[Vec2],
+    thickness: f32,
+    params: impl Into<ShapeParams>,
+) {
+    let params = params.into();
+    let (vertices, indices) = stroke(points, true, thickness, LineJoin::Miter, params.color);
+    draw_triangles(ctx, params, &vertices, &indices);
+}
+
+/// Draws connected lines through the points.
+///
+/// Lines do not overlap where they meet, so translucent lines are drawn evenly,
+/// except at sharp corners between lines shorter than their thickness.
+pub fn draw_polyline(
+    ctx: &mut Context,
+    points: &[Vec2],
+    thickness: f32,
+    join: LineJoin,
+    params: impl Into<ShapeParams>,
+) {
+    let params = params.into();
+    let (vertices, indices) = stroke(points, false, thickness, join, params.color);
+    draw_triangles(ctx, params, &vertices, &indices);
+}
+
+pub fn draw_rounded_rectangle(
+    ctx: &mut Context,
+    x: f32,
+    y: f32,
+    w: f32,
+    h: f32,
+    radius: f32,
+    params: impl Into<ShapeParams>,
+) {
+    let params = params.into();
+    let points = rounded_rectangle(x, y, w, h, radius);
+    let vertices = points
+        .iter()
+        .map(|point| vertex(*point, params.color))
+        .collect::<Vec<_>>();
+    draw_triangles(ctx, params, &vertices, &fan_closed(vertices.len()));
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn draw_rounded_rectangle_lines(
+    ctx: &mut Context,
+    x: f32,
+    y: f32,
+    w: f32,
+    h: f32,
+    radius: f32,
+    thickness: f32,
+    params: impl Into<ShapeParams>,
+) {
+    draw_polygon_lines(
+        ctx,
+        &rounded_rectangle(x, y, w, h, radius),
+        thickness,
+        params,
+    )
+}
+
+/// Draws a rectangle filled with a gradient. The color of the parameters tints the gradient.
+#[allow(clippy::too_many_arguments)]
+pub fn draw_rectangle_gradient(
+    ctx: &mut Context,
+    x: f32,
+    y: f32,
+    w: f32,
+    h: f32,
+    gradient: Gradient,
+    params: impl Into<ShapeParams>,
+) {
+    let params = params.into();
+    let [top_left, top_right, bottom_right, bottom_left] =
+        gradient.corners().map(|color| tint(color, params.color));
+    draw_triangles(
+        ctx,
+        params,
+        &[
+            vertex(vec2(x, y), top_left),
+            vertex(vec2(x + w, y), top_right),
+            vertex(vec2(x + w, y + h), bottom_right),
+            vertex(vec2(x, y + h), bottom_left),
+        ],
+        &[0, 1, 2, 0, 2, 3],
+    )
+}
+
+/// Color, blending and draw order of a shape.
+///
+/// A [Color] can be passed wherever shape parameters are taken.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ShapeParams {
+    pub color: Color,
+
+    /// How the shape is blended with what is under it.
+    /// If None - use the mode set with [set_blend_mode](super::set_blend_mode).
+    pub blend: Option<BlendMode>,
+
+    /// Where the shape is placed in the draw queue.
+    /// If None - draw immediately.
+    pub order: Option<DrawOrder>,
+}
+
+impl ShapeParams {
+    pub fn color(color: Color) -> Self {
+        Self {
+            color,
+            ..Default::default()
+        }
+    }
+
+    pub fn blend(color: Color, blend: BlendMode) -> Self {
+        Self {
+            color,
+            blend: Some(blend),
+            ..Default::default()
+        }
+    }
+}
+
+impl Default for ShapeParams {
+    fn default() -> Self {
+        Self {
+            color: Color::WHITE,
+            blend: None,
+            order: None,
+        }
+    }
+}
+
+impl From<Color> for ShapeParams {
+    fn from(color: Color) -> Self {
+        Self::color(color)
+    }
+}
+
+/// How the lines of a polyline are connected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LineJoin {
+    /// Extend the edges of the lines until they meet.
+    /// Falls back to [LineJoin::Bevel] for sharp angles.
+    #[default]
+    Miter,
+    /// Cut off the corner between the lines.
+    Bevel,
+    /// Round off the corner between the lines.
+    Round,
+}
+
+/// Colors of a gradient fill.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Gradient {
+    /// From the left color to the right one.
+    Horizontal(Color, Color),
+    /// From the top color to the bottom one.
+    Vertical(Color, Color),
+    /// Colors of each corner, clockwise from the top left.
+    Corners([Color; 4]),
+}
+
+impl Gradient {
+    /// Colors of each corner, clockwise from the top left.
+    pub fn corners(&self) -> [Color; 4] {
+        match *self {
+            Self::Horizontal(left, right) => [left, right, right, left],
+            Self::Vertical(top, bottom) => [top, top, bottom, bottom],
+            Self::Corners(corners) => corners,
+        }
+    }
+
+    /// The color at a position, from 0 to 1 across and down the gradient.
+    pub fn sample(&self, x: f32, y: f32) -> Color {
+        let [top_left, top_right, bottom_right, bottom_left] = self.corners();
+        top_left
+            .lerp(top_right, x)
+            .lerp(bottom_left.lerp(bottom_right, x), y)
+    }
+}
+
+/// Miter joins longer than this many times the thickness are beveled.
+const MITER_LIMIT: f32 = 4.0;
+
+/// Draws untextured triangles, or queues them if the shape has a draw order.
+fn draw_triangles(ctx: &mut Context, params: ShapeParams, vertices: &[Vertex], indices: &[u32]) {
+    match params.order {
+        Some(order) => ctx
+            .draw_queue
+            .push_geometry(order, vertices, indices, params.blend),
+        None => super::draw_geometry(ctx, None, params.blend, vertices, indices),
+    }
+}
+
+pub(crate) fn tint(color: Color, tint: Color) -> Color {
+    Color::new(
+        color.r * tint.r,
+        color.g * tint.g,
+        color.b * tint.b,
+        color.a * tint.a,
+    )
+}
+
+fn vertex(position: Vec2, color: Color) -> Vertex {
+    Vertex::new(position.x, position.y, 0.0, 0.0, 0.0, color)
+}
+
+/// Number of segments making up a full circle of the radius.
+fn segments(radius: f32) -> usize {
+    (radius.abs().sqrt() * 6.0).clamp(16.0, 128.0) as usize
+}
+
+/// Points along an ellipse from the start to the end angle, including both ends.
+fn ellipse_points(
+    center: Vec2,
+    rx: f32,
+    ry: f32,
+    start: f32,
+    end: f32,
+    full_segments: usize,
+) -> impl Iterator<Item = Vec2> {
+    let count = ((full_segments as f32 * (end - start).abs() / TAU).ceil() as usize).max(1);
+    (0..=count).map(move |i| {
+        let angle = start + (end - start) * i as f32 / count as f32;
+        center + vec2(angle.cos() * rx, angle.sin() * ry)
+    })
+}
+
+/// Indices of a fan around the first of the vertices, which is followed by `count` points along its edge.
+fn fan(count: usize) -> Vec<u32> {
+    (1..count as u32).flat_map(|i| [0, i, i + 1]).collect()
+}
+
+/// Indices of a fan filling a convex polygon of `count` points.
+fn fan_closed(count: usize) -> Vec<u32> {
+    (1..count.saturating_sub(1) as u32)
+        .flat_map(|i| [0, i, i + 1])
+        .collect()
+}
+
+fn draw_sector_ellipse(
+    ctx: &mut Context,
+    center: Vec2,
+    rx: f32,
+    ry: f32,
+    start: f32,
+    end: f32,
+    params: ShapeParams,
+) {
+    let mut vertices = vec![vertex(center, params.color)];
+    vertices.extend(
+        ellipse_points(center, rx, ry, start, end, segments(rx.max(ry)))
+            .map(|point| vertex(point, params.color)),
+    );
+    draw_triangles(ctx, params, &vertices, &fan(vertices.len() - 1));
+}
+
+/// Draws a band centered on the edge of an ellipse.
+#[allow(clippy::too_many_arguments)]
+fn draw_ring(
+    ctx: &mut Context,
+    center: Vec2,
+    rx: f32,
+    ry: f32,
+    start: f32,
+    end: f32,
+    thickness: f32,
+    params: ShapeParams,
+) {
+    let half = thickness / 2.0;
+    let outer = ellipse_points(
+        center,
+        rx + half,
+        ry + half,
+        start,
+        end,
+        segments(rx.max(ry)),
+    );
+    let inner = ellipse_points(
+        center,
+        (rx - half).max(0.0),
+        (ry - half).max(0.0),
+        start,
+        end,
+        segments(rx.max(ry)),
+    );
+    let vertices = outer
+        .zip(inner)
+        .flat_map(|(outer, inner)| [vertex(outer, params.color), vertex(inner, params.color)])
+        .collect::<Vec<_>>();
+    let indices = (0..(vertices.len() / 2).saturating_sub(1) as u32)
+        .flat_map(|i| {
+            let i = i * 2;
+            [i, i + 1, i + 3, i, i + 3, i + 2]
+        })
+        .collect::<Vec<_>>();
+    draw_triangles(ctx, params, &vertices, &indices);
+}
+
+/// Outline of a rounded rectangle, clockwise from the top left corner.
+fn rounded_rectangle(x: f32, y: f32, w: f32, h: f32, radius: f32) -> Vec<Vec2> {
+    let radius = radius.clamp(0.0, w.min(h) / 2.0);
+    let corners = [
+        (vec2(x + radius, y + radius), PI),
+        (vec2(x + w - radius, y + radius), PI * 1.5),
+        (vec2(x + w - radius, y + h - radius), 0.0),
+        (vec2(x + radius, y + h - radius), FRAC_PI_2),
+    ];
+    corners
+        .iter()
+        .flat_map(|(center, start)| {
+            ellipse_points(
+                *center,
+                radius,
+                radius,
+                *start,
+                start + FRAC_PI_2,
+                segments(radius),
+            )
+        })
+        .collect()
+}
+
+/// Builds the triangles of lines through the points, `thickness` wide.
+fn stroke(
+    points: &[Vec2],
+    closed: bool,
+    thickness: f32,
+    join: LineJoin,
+    color: Color,
+) -> (Vec<Vertex>, Vec<u32>) {
+    let mut points = points.to_vec();
+    points.dedup();
+    if closed && points.len() > 1 && points.first() == points.last() {
+        points.pop();
+    }
+
+    let mut vertices = Vec::new();
+    let mut indices = Vec::new();
+
+    if points.len() < 2 {
+        return (vertices, indices);
+    }
+
+    let half = thickness / 2.0;
+    let segment_count = if closed {
+        points.len()
+    } else {
+        points.len() - 1
+    };
+    let segment = |i: usize| (points[i % points.len()], points[(i + 1) % points.len()]);
+    let normal = |(from, to): (Vec2, Vec2)| {
+        let direction = (to - from).normalize();
+        vec2(-direction.y, direction.x)
+    };
+
+    let mut add = |positions: &[Vec2], triangles: &[u32]| {
+        let base = vertices.len() as u32;
+        vertices.extend(positions.iter().map(|position| vertex(*position, color)));
+        indices.extend(triangles.iter().map(|index| base + index));
+    };
+
+    let joins = if closed {
+        0..segment_count
+    } else {
+        1..segment_count
+    };
+
+    // where the inner edges of the lines meet at each join, and which side the outer edges are on,
+    // so the lines can end there instead of overlapping on the inside of the turn
+    let mut cuts = vec![None; points.len()];
+    for i in joins.clone() {
+        let previous = segment(i + points.len() - 1);
+        let next = segment(i);
+        let turn = (previous.1 - previous.0).perp_dot(next.1 - next.0);
+        if turn.abs() <= f32::EPSILON {
+            continue;
+        }
+        let side = -turn.signum();
+        let bisector = (normal(previous) + normal(next)).normalize_or_zero() * side;
+        let cos = bisector.dot(normal(previous) * side);
+        if cos <= f32::EPSILON {
+            continue;
+        }
+        // lines too short to be cut this far keep overlapping
+        let along = half * (1.0 - cos * cos).max(0.0).sqrt() / cos;
+        let fits = |(from, to): (Vec2, Vec2)| along <= from.distance(to) / 2.0;
+        if fits(previous) && fits(next) {
+            cuts[i] = Some((next.0 - bisector * half / cos, side));
+        }
+    }
+
+    for i in 0..segment_count {
+        let (from, to) = segment(i);
+        let offset = normal((from, to)) * half;
+        let (start, end) = (cuts[i], cuts[(i + 1) % points.len()]);
+
+        let mut outline = Vec::with_capacity(6);
+        match start {
+            Some((cut, side)) if side < 0.0 => outline.extend([from, cut]),
+            _ => outline.push(from + offset),
+        }
+        match end {
+            Some((cut, side)) if side < 0.0 => outline.extend([cut, to]),
+            _ => outline.push(to + offset),
+        }
+        match end {
+            Some((cut, side)) if side > 0.0 => outline.extend([to, cut]),
+            _ => outline.push(to - offset),
+        }
+        match start {
+            Some((cut, side)) if side > 0.0 => outline.extend([cut, from]),
+            _ => outline.push(from - offset),
+        }
+        add(&outline, &fan_closed(outline.len()));
+    }
+
+    for i in joins {
+        let previous = segment(i + points.len() - 1);
+        let next = segment(i);
+        let point = next.0;
+        let (incoming, outgoing) = (normal(previous), normal(next));
+        let turn = (previous.1 - previous.0).perp_dot(next.1 - next.0);
+        if turn.abs() <= f32::EPSILON {
+            continue;
+        }
+        let side = -turn.signum();
+        let (start, end) = (
+            point + incoming * half * side,
+            point + outgoing * half * side,
+        );
+
+        match join {
+            LineJoin::Miter | LineJoin::Bevel => {
+                let bisector = (incoming + outgoing).normalize_or_zero() * side;
+                let cos = bisector.dot(incoming * side);
+                if join == LineJoin::Miter && cos > 1.0 / MITER_LIMIT {
+                    let tip = point + bisector * half / cos;
+                    add(&[point, start, tip, end], &[0, 1, 2, 0, 2, 3]);
+                } else {
+                    add(&[point, start, end], &[0, 1, 2]);
+                }
+            }
+            LineJoin::Round => {
+                let from = (start - point).y.atan2((start - point).x);
+                let mut to = (end - point).y.atan2((end - point).x);
+                while to - from > PI {
+                    to -= TAU;
+                }
+                while from - to > PI {
+                    to += TAU;
+                }
+                let mut positions = vec![point];
+                positions.extend(ellipse_points(point, half, half, from, to, segments(half)));
+                add(&positions, &fan(positions.len() - 1));
+            }
+        }
+    }
+
+    (vertices, indices)
+}
+
+/// Splits a polygon into triangles by ear clipping, returning indices into its points.
+fn triangulate(points: &[Vec2]) -> Vec<u32> {
+    // repeated points block every ear, as points on the edge of a triangle count as inside it
+    let mut remaining = Vec::with_capacity(points.len());
+    for index in 0..points.len() as u32 {
+        if remaining.last().map(|last| points[*last as usize]) != Some(points[index as usize]) {
+            remaining.push(index);
+        }
+    }
+    while remaining.len() > 1
+        && points[remaining[0] as usize] == points[remaining[remaining.len() - 1] as usize]
+    {
+        remaining.pop();
+    }
+
+    if remaining.len() < 3 {
+        return Vec::new();
+    }
+
+    let area = (0..remaining.len())
+        .map(|i| {
+            let next = remaining[(i + 1) % remaining.len()];
+            points[remaining[i] as usize].perp_dot(points[next as usize])
+        })
+        .sum::<f32>();
+
+    // clip ears going the same way around every polygon
+    if area < 0.0 {
+        remaining.reverse();
+    }
+
+    let mut indices = Vec::with_capacity((remaining.len() - 2) * 3);
+    let mut i = 0;
+    let mut attempts = 0;
+
+    while remaining.len() > 3 {
+        let len = remaining.len();
+        i %= len;
+        let (a, b, c) = (
+            remaining[(i + len - 1) % len],
+            remaining[i],
+            remaining[(i + 1) % len],
+        );
+
+        // a polygon with crossing edges can run out of ears, so clip anyway to finish
+        if attempts > len || is_ear(points, &remaining, a, b, c) {
+            indices.extend([a, b, c]);
+            remaining.remove(i);
+            attempts = 0;
+        } else {
+            i += 1;
+            attempts += 1;
+        }
+    }
+
+    indices.extend(remaining);
+    indices
+}
+
+fn is_ear(points: &[Vec2], remaining: &[u32], a: u32, b: u32, c: u32) -> bool {
+    let (pa, pb, pc) = (points[a as usize], points[b as usize], points[c as usize]);
+    if (pb - pa).perp_dot(pc - pb) <= 0.0 {
+        return false;
+    }
+    !remaining
+        .iter()
+        .filter(|index| ![a, b, c].contains(index))
+        .any(|index| in_triangle(points[*index as usize], pa, pb, pc))
+}
+
+fn in_triangle(point: Vec2, a: Vec2, b: Vec2, c: Vec2) -> bool {
+    let ab = (b - a).perp_dot(point - a);
+    let bc = (c - b).perp_dot(point - b);
+    let ca = (a - c).perp_dot(point - c);
+    ab >= 0.0 && bc >= 0.0 && ca >= 0.0
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn area(points: &[Vec2]) -> f32 {
+        (0..points.len())
+            .map(|i| points[i].perp_dot(points[(i + 1) % points.len()]))
+            .sum::<f32>()
+            .abs()
+            / 2.0
+    }
+
+    /// Checks that the triangles cover the polygon without covering anything outside of it.
+    fn assert_fills(points: &[Vec2], expected: f32) {
+        let indices = triangulate(points);
+        assert_eq!(indices.len() % 3, 0);
+        let covered = indices
+            .chunks(3)
+            .map(|triangle| {
+                let [a, b, c] = [0, 1, 2].map(|i| points[triangle[i] as usize]);
+                area(&[a, b, c])
+            })
+            .sum::<f32>();
+        assert!((covered - expected).abs() < 1e-4, "{:?}", points);
+    }
+
+    #[test]
+    fn triangulates_concave() {
+        // an L shape and an arrow
+        let l = [
+            vec2(0.0, 0.0),
+            vec2(2.0, 0.0),
+            vec2(2.0, 1.0),
+            vec2(1.0, 1.0),
+            vec2(1.0, 2.0),
+            vec2(0.0, 2.0),
+        ];
+        assert_fills(&l, 3.0);
+        let arrow = [
+            vec2(0.0, 0.0),
+            vec2(4.0, 2.0),
+            vec2(0.0, 4.0),
+            vec2(1.0, 2.0),
+        ];
+        assert_fills(&arrow, area(&arrow));
+        assert_eq!(triangulate(&l).len(), 12);
+    }
+
+    #[test]
+    fn triangulates_clockwise() {
+        let mut l = [
+            vec2(0.0, 0.0),
+            vec2(2.0, 0.0),
+            vec2(2.0, 1.0),
+            vec2(1.0, 1.0),
+            vec2(1.0, 2.0),
+            vec2(0.0, 2.0),
+        ];
+        l.reverse();
+        assert_fills(&l, 3.0);
+    }
+
+    #[test]
+    fn skips_repeated_points() {
+        let closed = [
+            vec2(0.0, 0.0),
+            vec2(2.0, 0.0),
+            vec2(2.0, 1.0),
+            vec2(1.0, 1.0),
+            vec2(1.0, 2.0),
+            vec2(0.0, 2.0),
+            vec2(0.0, 0.0),
+        ];
+        assert_fills(&closed, 3.0);
+        assert_eq!(triangulate(&closed).len(), 12);
+
+        let repeated = [
+            vec2(0.0, 0.0),
+            vec2(2.0, 0.0),
+            vec2(2.0, 0.0),
+            vec2(2.0, 1.0),
+            vec2(1.0, 1.0),
+            vec2(1.0, 1.0),
+            vec2(1.0, 2.0),
+            vec2(0.0, 2.0),
+        ];
+        assert_fills(&repeated, 3.0);
+
+        assert!(triangulate(&[vec2(0.0, 0.0), vec2(1.0, 0.0), vec2(0.0, 0.0)]).is_empty());
+    }
+
+    #[test]
+    fn triangulates_collinear() {
+        let square = [
+            vec2(0.0, 0.0),
+            vec2(1.0, 0.0),
+            vec2(2.0, 0.0),
+            vec2(2.0, 2.0),
+            vec2(1.0, 2.0),
+            vec2(0.0, 2.0),
+            vec2(0.0, 1.0),
+        ];
+        assert_fills(&square, 4.0);
+        assert_fills(&[vec2(0.0, 0.0), vec2(1.0, 0.0), vec2(2.0, 0.0)], 0.0);
+    }
 }
@@ -0,0 +1,461 @@
+use std::rc::Rc;
+
+use crate::{
+    context::Context,
+    error::{EngineError, ParseError},
+    math::{vec2, Rectangle, Vec2},
+    utils::HashMap,
+};
+
+use super::{Color, DrawParams, Image, Texture};
+
+/// A font that can be drawn with [draw_text].
+#[derive(Debug, Clone)]
+pub struct Font(Rc<FontData>);
+
+#[derive(Debug)]
+enum FontData {
+    Bitmap(BitmapFont),
+    TrueType {
+        font: macroquad::text::Font,
+        size: u16,
+        ascent: f32,
+    },
+}
+
+#[derive(Debug)]
+struct BitmapFont {
+    pages: Vec<Texture>,
+    glyphs: HashMap<char, Glyph>,
+    kerning: HashMap<(char, char), f32>,
+    line_height: f32,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Glyph {
+    page: usize,
+    source: Rectangle,
+    offset: Vec2,
+    advance: f32,
+}
+
+/// Horizontal alignment of each line of text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextAlign {
+    Left,
+    Center,
+    Right,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct TextParams {
+    pub color: Color,
+
+    /// Size multiplier of the font.
+    pub scale: f32,
+
+    /// Where lines are positioned relative to the x coordinate, or inside the wrap width if set.
+    pub align: TextAlign,
+
+    /// Width lines are broken at, between words where possible.
+    /// Is None by default
+    pub wrap: Option<f32>,
+
+    /// Extra space added between lines.
+    pub line_spacing: f32,
+}
+
+impl Font {
+    /// Load a font in the text BMFont (.fnt) format.
+    /// `pages` are the images listed in the file, in order of page id.
+    pub fn bmfont(ctx: &mut Context, data: &str, pages: &[Image]) -> Result<Self, ParseError> {
+        const FORMAT: &str = "bmfont";
+
+        let mut glyphs = HashMap::default();
+        let mut kerning = HashMap::default();
+        let mut line_height = 0.0;
+
+        for (line, text) in data.lines().enumerate() {
+            let (tag, values) = bmfont_values(text);
+            let get = |key: &str| -> Result<f32, ParseError> {
+                values
+                    .iter()
+                    .find(|(k, ..)| *k == key)
+                    .and_then(|(.., value)| value.parse().ok())
+                    .ok_or_else(|| {
+                        ParseError::new(FORMAT, format!("missing {} on line {}", key, line + 1))
+                    })
+            };
+            let character = |key: &str| -> Result<char, ParseError> {
+                get(key).map(|id| char::from_u32(id as u32).unwrap_or(char::REPLACEMENT_CHARACTER))
+            };
+            match tag {
+                "common" => line_height = get("lineHeight")?,
+                "char" => {
+                    let page = get("page").unwrap_or_default() as usize;
+                    if page >= pages.len() {
+                        return Err(ParseError::new(
+                            FORMAT,
+                            format!("missing image for page {}", page),
+                        ));
+                    }
+                    glyphs.insert(
+                        character("id")?,
+                        Glyph {
+                            page,
+                            source: Rectangle::new(
+                                get("x")?,
+                                get("y")?,
+                                get("width")?,
+                                get("height")?,
+                            ),
+                            offset: vec2(get("xoffset")?, get("yoffset")?),
+                            advance: get("xadvance")?,
+                        },
+                    );
+                }
+                "kerning" => {
+                    kerning.insert((character("first")?, character("second")?), get("amount")?);
+                }
+                _ => (),
+            }
+        }
+
+        Ok(Self(Rc::new(FontData::Bitmap(BitmapFont {
+            pages: pages
+                .iter()
+                .map(|page| Texture::from_image(ctx, page))
+                .collect(),
+            glyphs,
+            kerning,
+            line_height,
+        }))))
+    }
+
+    /// The image files used by a BMFont (.fnt) file, in order of page id.
+    pub fn bmfont_pages(data: &str) -> Vec<String> {
+        let mut pages = data
+            .lines()
+            .map(bmfont_values)
+            .filter(|(tag, ..)| *tag == "page")
+            .filter_map(|(.., values)| {
+                let get = |key| values.iter().find(|(k, ..)| *k == key).map(|(.., v)| *v);
+                Some((get("id")?.parse::<usize>().ok()?, get("file")?.to_owned()))
+            })
+            .collect::<Vec<_>>();
+        pages.sort_by_key(|(id, ..)| *id);
+        pages.into_iter().map(|(.., file)| file).collect()
+    }
+
+    /// Load a font from an image split into a grid of equally sized glyphs.
+    /// The glyphs are given by `characters` in order, left to right and top to bottom.
+    pub fn grid(
+        ctx: &mut Context,
+        image: &Image,
+        glyph_width: u32,
+        glyph_height: u32,
+        characters: &str,
+    ) -> Self {
+        let columns = (image.width() / glyph_width.max(1)).max(1);
+        let (width, height) = (glyph_width as f32, glyph_height as f32);
+
+        let glyphs = characters
+            .chars()
+            .enumerate()
+            .map(|(index, character)| {
+                let index = index as u32;
+                (
+                    character,
+                    Glyph {
+                        page: 0,
+                        source: Rectangle::new(
+                            (index % columns) as f32 * width,
+                            (index / columns) as f32 * height,
+                            width,
+                            height,
+                        ),
+                        offset: Vec2::ZERO,
+                        advance: width,
+                    },
+                )
+            })
+            .collect();
+
+        Self(Rc::new(FontData::Bitmap(BitmapFont {
+            pages: vec![Texture::from_image(ctx, image)],
+            glyphs,
+            kerning: Default::default(),
+            line_height: height,
+        })))
+    }
+
+    /// Load a TrueType (.ttf) font, rasterized at the given size.
+    #[allow(unused_variables)]
+    pub fn truetype(ctx: &mut Context, data: &[u8], size: u16) -> Result<Self, EngineError> {
+        let font = macroquad::text::load_ttf_font_from_bytes(data).map_err(EngineError::Font)?;
+        let ascent = macroquad::text::measure_text("ÁHgj|", Some(font), size, 1.0).offset_y;
+        Ok(Self(Rc::new(FontData::TrueType { font, size, ascent })))
+    }
+
+    /// Height of a line of text before scaling.
+    pub fn line_height(&self) -> f32 {
+        match &*self.0 {
+            FontData::Bitmap(font) => font.line_height,
+            FontData::TrueType { size, .. } => *size as f32,
+        }
+    }
+
+    /// Horizontal distance moved after drawing a character, before scaling.
+    /// `previous` is the character drawn before it, used for kerning.
+    pub fn advance(&self, character: char, previous: Option<char>) -> f32 {
+        match &*self.0 {
+            FontData::Bitmap(font) => {
+                font.glyphs
+                    .get(&character)
+                    .map(|glyph| glyph.advance)
+                    .unwrap_or_default()
+                    + font.kerning(previous, character)
+            }
+            FontData::TrueType { font, size, .. } => {
+                macroquad::text::measure_text(
+                    character.encode_utf8(&mut [0; 4]),
+                    Some(*font),
+                    *size,
+                    1.0,
+                )
+                .width
+            }
+        }
+    }
+
+    /// Space added between two characters, before scaling.
+    pub(crate) fn kerning(&self, previous: Option<char>, character: char) -> f32 {
+        match &*self.0 {
+            FontData::Bitmap(font) => font.kerning(previous, character),
+            FontData::TrueType { .. } => 0.0,
+        }
+    }
+
+    /// Width of a single line of text before scaling.
+    pub fn line_width(&self, line: &str) -> f32 {
+        let mut previous = None;
+        line.chars()
+            .map(|character| {
+                let advance = self.advance(character, previous);
+                previous = Some(character);
+                advance
+            })
+            .sum()
+    }
+
+    /// Splits text into the lines it is drawn as.
+    pub fn wrap<'a>(&self, text: &'a str, width: Option<f32>, scale: f32) -> Vec<&'a str> {
+        let mut lines = Vec::new();
+        for line in text.lines() {
+            match width {
+                Some(width) => self.wrap_line(line, width / scale, &mut lines),
+                None => lines.push(line),
+            }
+        }
+        lines
+    }
+
+    fn wrap_line<'a>(&self, line: &'a str, width: f32, lines: &mut Vec<&'a str>) {
+        let mut start = 0;
+        let mut x = 0.0;
+        let mut space = None;
+        let mut previous = None;
+
+        for (index, character) in line.char_indices() {
+            let advance = self.advance(character, previous);
+            if character == ' ' {
+                space = Some(index);
+            } else if x + advance > width && index > start {
+                match space {
+                    Some(space) if space > start => {
+                        lines.push(line[start..space].trim_end());
+                        start = space + 1;
+                    }
+                    _ => {
+                        lines.push(&line[start..index]);
+                        start = index;
+                    }
+                }
+                space = None;
+                x = self.line_width(&line[start..index]);
+            }
+            x += advance;
+            previous = Some(character);
+        }
+
+        lines.push(&line[start..]);
+    }
+
+    /// Draws a character with its top left corner at the given position.
+    pub(crate) fn draw_char(
+        &self,
+        ctx: &mut Context,
+        character: char,
+        x: f32,
+        y: f32,
+        color: Color,
+        scale: f32,
+    ) {
+        match &*self.0 {
+            FontData::Bitmap(font) => {
+                if let Some(glyph) = font.glyphs.get(&character) {
+                    font.pages[glyph.page].draw(
+                        ctx,
+                        x + glyph.offset.x * scale,
+                        y + glyph.offset.y * scale,
+                        DrawParams {
+                            color,
+                            source: Some(glyph.source),
+                            dest_size: Some(glyph.source.size() * scale),
+                            ..Default::default()
+                        },
+                    );
+                }
+            }
+            FontData::TrueType { .. } => {
+                self.draw_line(ctx, character.encode_utf8(&mut [0; 4]), x, y, color, scale)
+            }
+        }
+    }
+
+    fn draw_line(&self, ctx: &mut Context, line: &str, x: f32, y: f32, color: Color, scale: f32) {
+        match &*self.0 {
+            FontData::Bitmap(font) => {
+                let mut x = x;
+                let mut previous = None;
+                for character in line.chars() {
+                    let kerning = font.kerning(previous, character) * scale;
+                    self.draw_char(ctx, character, x + kerning, y, color, scale);
+                    x += self.advance(character, previous) * scale;
+                    previous = Some(character);
+                }
+            }
+            FontData::TrueType { font, size, ascent } => {
+                super::apply_blend_mode(ctx, None);
+                macroquad::text::draw_text_ex(
+                    line,
+                    x,
+                    y + ascent * scale,
+                    macroquad::text::TextParams {
+                        font: *font,
+                        font_size: *size,
+                        font_scale: scale,
+                        color,
+                        ..Default::default()
+                    },
+                )
+            }
+        }
+    }
+}
+
+impl BitmapFont {
+    fn kerning(&self, previous: Option<char>, character: char) -> f32 {
+        previous
+            .and_then(|previous| self.kerning.get(&(previous, character)))
+            .copied()
+            .unwrap_or_default()
+    }
+}
+
+/// Splits a line of a BMFont file into its tag and key value pairs.
+fn bmfont_values(line: &str) -> (&str, Vec<(&str, &str)>) {
+    let line = line.trim();
+    let (tag, mut rest) = line.split_at(line.find(' ').unwrap_or(line.len()));
+    let mut values = Vec::new();
+    loop {
+        rest = rest.trim_start();
+        let equals = match rest.find('=') {
+            Some(equals) => equals,
+            None => break,
+        };
+        let key = &rest[..equals];
+        rest = &rest[equals + 1..];
+        let value = match rest.strip_prefix('"') {
+            Some(quoted) => {
+                let end = quoted.find('"').unwrap_or(quoted.len());
+                rest = quoted.get(end + 1..).unwrap_or_default();
+                &quoted[..end]
+            }
+            None => {
+                let end = rest.find(' ').unwrap_or(rest.len());
+                let value = &rest[..end];
+                rest = &rest[end..];
+                value
+            }
+        };
+        values.push((key, value));
+    }
+    (tag, values)
+}
+
+impl TextParams {
+    pub fn color(color: Color) -> Self {
+        Self {
+            color,
+            ..Default::default()
+        }
+    }
+
+    /// X position of a line relative to the drawn position.
+    pub(crate) fn line_offset(&self, line_width: f32) -> f32 {
+        let area = self.wrap.unwrap_or_default();
+        match self.align {
+            TextAlign::Left => 0.0,
+            TextAlign::Center => (area - line_width) / 2.0,
+            TextAlign::Right => area - line_width,
+        }
+    }
+}
+
+impl Default for TextParams {
+    fn default() -> Self {
+        Self {
+            color: Color::WHITE,
+            scale: 1.0,
+            align: TextAlign::Left,
+            wrap: None,
+            line_spacing: 0.0,
+        }
+    }
+}
+
+/// Draws text with the top of the first line at `y`.
+pub fn draw_text(ctx: &mut Context, font: &Font, text: &str, x: f32, y: f32, params: TextParams) {
+    let line_height = (font.line_height() + params.line_spacing) * params.scale;
+    for (index, line) in font
+        .wrap(text, params.wrap, params.scale)
+        .into_iter()
+        .enumerate()
+    {
+        let width = font.line_width(line) * params.scale;
+        font.draw_line(
+            ctx,
+            line,
+            x + params.line_offset(width),
+            y + index as f32 * line_height,
+            params.color,
+            params.scale,
+        );
+    }
+}
+
+/// Size of the area text is drawn in.
+#[allow(unused_variables)]
+pub fn measure_text(ctx: &Context, font: &Font, text: &str, params: TextParams) -> Vec2 {
+    let lines = font.wrap(text, params.wrap, params.scale);
+    let width = lines
+        .iter()
+        .map(|line| font.line_width(line))
+        .fold(0.0, f32::max);
+    let height = match lines.len() {
+        0 => 0.0,
+        len => len as f32 * font.line_height() + (len - 1) as f32 * params.line_spacing,
+    };
+    vec2(width, height) * params.scale
+}
@@ -1,40 +1,177 @@
-use macroquad::prelude::{DrawTextureParams, FilterMode, Texture2D};
-use std::rc::Rc;
+use image::imageops;
+use macroquad::{
+    miniquad::gl,
+    prelude::{get_internal_gl, DrawTextureParams, Texture2D},
+};
+use std::{cell::Cell, rc::Rc};
+
+pub use macroquad::{miniquad::TextureWrap, prelude::FilterMode};
 
 use crate::{
     context::Context,
+    error::EngineError,
     math::{Rectangle, Vec2},
 };
 
-use super::{Color, Image};
+use super::{image::dimension_mismatch, BlendMode, Color, DrawOrder, Image};
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct Texture(Rc<TextureData>);
 
+/// How a texture is sampled when drawn.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TextureOptions {
+    pub filter: FilterMode,
+
+    /// How texture coordinates outside of the texture are handled.
+    /// Repeating textures should have power of two sizes to work on the web.
+    pub wrap: TextureWrap,
+
+    /// Generate smaller copies of the texture, used when it is drawn scaled down.
+    pub mipmaps: bool,
+}
+
+impl Default for TextureOptions {
+    fn default() -> Self {
+        Self {
+            filter: FilterMode::Nearest,
+            wrap: TextureWrap::Clamp,
+            mipmaps: false,
+        }
+    }
+}
+
 impl Texture {
-    pub(crate) fn crate_new(data: &[u8]) -> Result<Self, image::ImageError> {
-        let image = image::load_from_memory_with_format(data, image::ImageFormat::Png)?.to_rgba8();
-        Ok(Self::crate_from_image(&image))
+    pub(crate) fn crate_new(data: &[u8]) -> Result<Self, EngineError> {
+        Ok(Self::crate_from_image(&super::decode(data)?))
     }
 
     pub(crate) fn crate_from_image(image: &image::RgbaImage) -> Self {
-        let tex = Texture2D::from_rgba8(image.width() as _, image.height() as _, image.as_raw());
-        tex.set_filter(FilterMode::Nearest);
-        Self(Rc::new(TextureData(tex)))
+        Self::crate_from_image_with_options(image, Default::default())
+    }
+
+    pub(crate) fn crate_from_image_with_options(
+        image: &image::RgbaImage,
+        options: TextureOptions,
+    ) -> Self {
+        let texture = Self::crate_from_raw(Texture2D::from_rgba8(
+            image.width() as _,
+            image.height() as _,
+            image.as_raw(),
+        ));
+        texture.0.options.set(options);
+        if options.mipmaps {
+            texture.upload_mipmaps(image);
+        }
+        texture.apply_options();
+        texture
+    }
+
+    pub(crate) fn crate_from_raw(texture: Texture2D) -> Self {
+        let texture = Self(Rc::new(TextureData {
+            texture,
+            options: Default::default(),
+        }));
+        texture.apply_options();
+        texture
     }
 
     #[allow(unused_variables)]
-    pub fn new(ctx: &mut Context, data: &[u8]) -> Result<Self, image::ImageError> {
+    pub fn new(ctx: &mut Context, data: &[u8]) -> Result<Self, EngineError> {
         Self::crate_new(data)
     }
 
+    #[allow(unused_variables)]
+    pub fn with_options(
+        ctx: &mut Context,
+        data: &[u8],
+        options: TextureOptions,
+    ) -> Result<Self, EngineError> {
+        Ok(Self::crate_from_image_with_options(
+            &super::decode(data)?,
+            options,
+        ))
+    }
+
     #[allow(unused_variables)]
     pub fn from_image(ctx: &mut Context, image: &Image) -> Self {
         Self::crate_from_image(&image.0)
     }
 
     #[allow(unused_variables)]
+    pub fn from_image_with_options(
+        ctx: &mut Context,
+        image: &Image,
+        options: TextureOptions,
+    ) -> Self {
+        Self::crate_from_image_with_options(&image.0, options)
+    }
+
+    /// Replaces the contents of the texture with an image of the same size.
+    ///
+    /// Fails if the image is not the size of the texture.
+    /// Flushes the [draw queue](super::flush_draw_queue), so draw calls queued before this
+    /// are not sorted with ones queued after it.
+    pub fn update(&self, ctx: &mut Context, image: &Image) -> Result<(), EngineError> {
+        if image.width() != self.width() as u32 || image.height() != self.height() as u32 {
+            return Err(dimension_mismatch());
+        }
+        super::flush_draw_queue(ctx);
+        let mut gl = unsafe { get_internal_gl() };
+        gl.flush();
+        self.0
+            .raw_miniquad_texture_handle()
+            .update(gl.quad_context, image.0.as_raw());
+        if self.0.options.get().mipmaps {
+            self.upload_mipmaps(&image.0);
+        }
+        Ok(())
+    }
+
+    /// Replaces part of the texture, placing the image's top left corner at the given position.
+    /// Mipmaps are regenerated from the whole texture, which is slow.
+    ///
+    /// Fails if the image does not fit in the texture.
+    /// Flushes the [draw queue](super::flush_draw_queue), so draw calls queued before this
+    /// are not sorted with ones queued after it.
+    pub fn update_part(
+        &self,
+        ctx: &mut Context,
+        x: u32,
+        y: u32,
+        image: &Image,
+    ) -> Result<(), EngineError> {
+        let fits = |start: u32, size: u32, limit: f32| matches!(start.checked_add(size), Some(end) if end <= limit as u32);
+        if !fits(x, image.width(), self.width()) || !fits(y, image.height(), self.height()) {
+            return Err(dimension_mismatch());
+        }
+        super::flush_draw_queue(ctx);
+        let mut gl = unsafe { get_internal_gl() };
+        gl.flush();
+        self.0.raw_miniquad_texture_handle().update_texture_part(
+            gl.quad_context,
+            x as _,
+            y as _,
+            image.width() as _,
+            image.height() as _,
+            image.0.as_raw(),
+        );
+        if self.0.options.get().mipmaps {
+            let data = self.0.get_texture_data();
+            if let Some(image) =
+                image::RgbaImage::from_raw(data.width as _, data.height as _, data.bytes)
+            {
+                self.upload_mipmaps(&image);
+            }
+        }
+        Ok(())
+    }
+
     pub fn draw(&self, ctx: &mut Context, x: f32, y: f32, params: DrawParams) {
+        if let Some(order) = params.order {
+            return ctx.draw_queue.push_texture(order, self, x, y, params);
+        }
+        super::apply_blend_mode(ctx, params.blend);
         self.crate_draw(x, y, params)
     }
 
@@ -51,8 +188,88 @@ impl Texture {
         self.0.height()
     }
 
+    pub fn options(&self) -> TextureOptions {
+        self.0.options.get()
+    }
+
     pub fn set_filter(&self, filter: FilterMode) {
-        self.0.set_filter(filter)
+        self.0.options.set(TextureOptions {
+            filter,
+            ..self.0.options.get()
+        });
+        self.apply_options();
+    }
+
+    pub fn set_wrap(&self, wrap: TextureWrap) {
+        self.0.options.set(TextureOptions {
+            wrap,
+            ..self.0.options.get()
+        });
+        self.apply_options();
+    }
+
+    fn apply_options(&self) {
+        let options = self.0.options.get();
+        let texture = self.0.raw_miniquad_texture_handle();
+        let gl = unsafe { get_internal_gl() };
+        texture.set_filter(gl.quad_context, options.filter);
+        texture.set_wrap(gl.quad_context, options.wrap);
+        if options.mipmaps {
+            let min_filter = match options.filter {
+                FilterMode::Linear => gl::GL_LINEAR_MIPMAP_LINEAR,
+                FilterMode::Nearest => gl::GL_NEAREST_MIPMAP_NEAREST,
+            };
+            self.bound(|| unsafe {
+                gl::glTexParameteri(
+                    gl::GL_TEXTURE_2D,
+                    gl::GL_TEXTURE_MIN_FILTER,
+                    min_filter as _,
+                )
+            });
+        }
+    }
+
+    /// Uploads every mipmap level below the full size image.
+    fn upload_mipmaps(&self, image: &image::RgbaImage) {
+        self.bound(|| {
+            let (mut width, mut height) = image.dimensions();
+            let mut level = 0;
+            while width > 1 || height > 1 {
+                width = (width / 2).max(1);
+                height = (height / 2).max(1);
+                level += 1;
+                let mipmap = imageops::resize(image, width, height, imageops::FilterType::Triangle);
+                unsafe {
+                    gl::glPixelStorei(gl::GL_UNPACK_ALIGNMENT, 1);
+                    gl::glTexImage2D(
+                        gl::GL_TEXTURE_2D,
+                        level,
+                        gl::GL_RGBA as _,
+                        width as _,
+                        height as _,
+                        0,
+                        gl::GL_RGBA,
+                        gl::GL_UNSIGNED_BYTE,
+                        mipmap.as_ptr() as *const _,
+                    );
+                }
+            }
+        })
+    }
+
+    /// Runs raw GL calls with the texture bound, restoring the previous binding after.
+    fn bound(&self, f: impl FnOnce()) {
+        const GL_TEXTURE_BINDING_2D: u32 = 0x8069;
+        let mut previous = 0;
+        unsafe {
+            gl::glGetIntegerv(GL_TEXTURE_BINDING_2D, &mut previous);
+            gl::glBindTexture(
+                gl::GL_TEXTURE_2D,
+                self.0.raw_miniquad_texture_handle().gl_internal_id(),
+            );
+        }
+        f();
+        unsafe { gl::glBindTexture(gl::GL_TEXTURE_2D, previous as _) };
     }
 
     pub fn data(&self) -> &TextureData {
@@ -63,25 +280,28 @@ impl Texture {
 }
 
 #[derive(Debug, PartialEq)]
-pub struct TextureData(Texture2D);
+pub struct TextureData {
+    texture: Texture2D,
+    options: Cell<TextureOptions>,
+}
 
 impl core::ops::Deref for TextureData {
     type Target = Texture2D;
 
     fn deref(&self) -> &Self::Target {
-        &self.0
+        &self.texture
     }
 }
 
 impl core::ops::DerefMut for TextureData {
     fn deref_mut(&mut self) -> &mut Self::Target {
-        &mut self.0
+        &mut self.texture
     }
 }
 
 impl Drop for TextureData {
     fn drop(&mut self) {
-        self.0.delete()
+        self.texture.delete()
     }
 }
 
@@ -106,6 +326,14 @@ pub struct DrawParams {
     pub flip_y: bool,
 
     pub origin: Option<Vec2>,
+
+    /// How the texture is blended with what is under it.
+    /// If None - use the mode set with [set_blend_mode](super::set_blend_mode).
+    pub blend: Option<BlendMode>,
+
+    /// Where the texture is placed in the draw queue.
+    /// If None - draw immediately.
+    pub order: Option<DrawOrder>,
 }
 
 impl DrawParams {
@@ -148,6 +376,8 @@ impl Default for DrawParams {
             flip_x: false,
             flip_y: false,
             origin: None,
+            blend: None,
+            order: None,
         }
     }
 }
@@ -0,0 +1,1192 @@
+//! Loading of maps and tilesets made in Tiled, in both the XML (`.tmx`, `.tsx`)
+//! and JSON (`.tmj`, `.tsj`) formats.
+//!
+//! Only orthogonal, finite maps are supported. Image layers are skipped.
+
+use serde::Deserialize;
+
+use crate::{
+    error::ParseError,
+    math::{vec2, Vec2},
+};
+
+use super::{
+    Color, Layer, LayerKind, MapObject, ObjectShape, Properties, Property, Tile, TileData,
+    TileFrame, TileLayer, Tilemap, Tileset,
+};
+
+const FORMAT: &str = "tiled map";
+
+impl Tilemap {
+    /// Reads a map saved in Tiled's XML format.
+    ///
+    /// Tilesets kept in their own files are added without any tiles
+    /// until they are loaded with [Tilemap::resolve_tileset].
+    pub fn tmx(data: &str) -> Result<Self, ParseError> {
+        tmx::map(&xml::parse(data)?)
+    }
+
+    /// Reads a map saved in Tiled's JSON format.
+    ///
+    /// Tilesets kept in their own files are added without any tiles
+    /// until they are loaded with [Tilemap::resolve_tileset].
+    pub fn tmj(data: &str) -> Result<Self, ParseError> {
+        tmj::map(data)
+    }
+
+    /// Paths of the tilesets kept in their own files that have not been loaded yet.
+    pub fn external_tilesets(&self) -> Vec<&str> {
+        self.tilesets
+            .iter()
+            .filter(|tileset| !tileset.loaded)
+            .flat_map(|tileset| tileset.source.as_deref())
+            .collect()
+    }
+
+    /// Loads a tileset kept in its own file, in either the XML or JSON format.
+    /// The tileset's image path is made relative to the map.
+    pub fn resolve_tileset(&mut self, source: &str, data: &str) -> Result<(), ParseError> {
+        for tileset in self
+            .tilesets
+            .iter_mut()
+            .filter(|tileset| tileset.source.as_deref() == Some(source))
+        {
+            let mut loaded = match data.trim_start().starts_with('<') {
+                true => Tileset::tsx(data, tileset.first_gid)?,
+                false => Tileset::tsj(data, tileset.first_gid)?,
+            };
+            loaded.source = Some(source.to_owned());
+            loaded.image = loaded.image.map(|image| {
+                std::path::Path::new(source)
+                    .parent()
+                    .map(|parent| parent.join(&image).to_string_lossy().into_owned())
+                    .unwrap_or(image)
+            });
+            *tileset = loaded;
+        }
+        Ok(())
+    }
+}
+
+impl Tileset {
+    /// Reads a tileset saved in Tiled's XML format.
+    pub fn tsx(data: &str, first_gid: u32) -> Result<Self, ParseError> {
+        tmx::tileset(&xml::parse(data)?, first_gid)
+    }
+
+    /// Reads a tileset saved in Tiled's JSON format.
+    pub fn tsj(data: &str, first_gid: u32) -> Result<Self, ParseError> {
+        let tileset =
+            serde_json::from_str(data).map_err(|err| ParseError::new(FORMAT, err.to_string()))?;
+        tmj::tileset(tileset, first_gid)
+    }
+}
+
+/// Visibility, opacity and offset passed down from group layers.
+#[derive(Clone, Copy)]
+struct Group {
+    visible: bool,
+    opacity: f32,
+    offset: Vec2,
+}
+
+impl Default for Group {
+    fn default() -> Self {
+        Self {
+            visible: true,
+            opacity: 1.0,
+            offset: Vec2::ZERO,
+        }
+    }
+}
+
+impl Group {
+    fn layer(&self, name: String, visible: bool, opacity: f32, offset: Vec2) -> Layer {
+        Layer {
+            name,
+            visible: self.visible && visible,
+            opacity: self.opacity * opacity,
+            offset: self.offset + offset,
+            properties: Properties::default(),
+            kind: LayerKind::Objects(Vec::new()),
+        }
+    }
+}
+
+fn check_map(orientation: &str, infinite: bool) -> Result<(), ParseError> {
+    if orientation != "orthogonal" {
+        return Err(ParseError::new(
+            FORMAT,
+            format!("{} maps are not supported", orientation),
+        ));
+    }
+    if infinite {
+        return Err(ParseError::new(FORMAT, "infinite maps are not supported"));
+    }
+    Ok(())
+}
+
+/// Placeholder for a tileset kept in its own file.
+fn external_tileset(source: String, first_gid: u32) -> Tileset {
+    let mut tileset = Tileset::new(String::new(), first_gid, 0.0, 0.0);
+    tileset.source = Some(source);
+    tileset.loaded = false;
+    tileset
+}
+
+/// Number of tiles in a layer of the given size.
+fn layer_size(width: u32, height: u32) -> Result<usize, ParseError> {
+    (width as usize)
+        .checked_mul(height as usize)
+        .ok_or_else(|| {
+            ParseError::new(
+                FORMAT,
+                format!("layer of {}x{} tiles is too large", width, height),
+            )
+        })
+}
+
+fn tile_layer(width: u32, height: u32, tiles: Vec<u32>) -> Result<TileLayer, ParseError> {
+    let len = layer_size(width, height)?;
+    if tiles.len() != len {
+        return Err(ParseError::new(
+            FORMAT,
+            format!("layer has {} tiles instead of {}", tiles.len(), len),
+        ));
+    }
+    Ok(TileLayer {
+        width,
+        height,
+        tiles: tiles.into_iter().map(Tile::from_raw).collect(),
+    })
+}
+
+/// Reads base64 encoded tile data, which may be compressed.
+/// Decompression stops once there is more data than a layer of the size can hold.
+fn decode_tiles(
+    data: &str,
+    compression: Option<&str>,
+    width: u32,
+    height: u32,
+) -> Result<Vec<u32>, ParseError> {
+    use base64::Engine;
+
+    let data = data.split_whitespace().collect::<String>();
+    let bytes = base64::engine::general_purpose::STANDARD
+        .decode(data)
+        .map_err(|err| ParseError::new(FORMAT, format!("invalid base64 tile data: {}", err)))?;
+
+    let limit = layer_size(width, height)?
+        .saturating_add(1)
+        .saturating_mul(4) as u64;
+    let bytes = match compression {
+        None | Some("") => bytes,
+        Some("zlib") => decompress(flate2::read::ZlibDecoder::new(bytes.as_slice()), limit)?,
+        Some("gzip") => decompress(flate2::read::GzDecoder::new(bytes.as_slice()), limit)?,
+        Some(other) => {
+            return Err(ParseError::new(
+                FORMAT,
+                format!("unsupported compression \"{}\"", other),
+            ))
+        }
+    };
+
+    Ok(bytes
+        .chunks_exact(4)
+        .map(|gid| u32::from_le_bytes([gid[0], gid[1], gid[2], gid[3]]))
+        .collect())
+}
+
+/// Reads up to `limit` bytes from a decompressing reader.
+fn decompress(reader: impl std::io::Read, limit: u64) -> Result<Vec<u8>, ParseError> {
+    use std::io::Read;
+
+    let mut bytes = Vec::new();
+    reader
+        .take(limit)
+        .read_to_end(&mut bytes)
+        .map_err(|err| ParseError::new(FORMAT, format!("invalid compressed tile data: {}", err)))?;
+    Ok(bytes)
+}
+
+fn csv_tiles(data: &str) -> Result<Vec<u32>, ParseError> {
+    data.split(',')
+        .map(str::trim)
+        .filter(|gid| !gid.is_empty())
+        .map(|gid| {
+            gid.parse()
+                .map_err(|_| ParseError::new(FORMAT, format!("invalid tile \"{}\"", gid)))
+        })
+        .collect()
+}
+
+/// Reads a color written as `#RRGGBB` or `#AARRGGBB`.
+fn color(value: &str) -> Result<Color, ParseError> {
+    let invalid = || ParseError::new(FORMAT, format!("invalid color \"{}\"", value));
+    let hex = value.trim_start_matches('#');
+    let channel = |i: usize| {
+        hex.get(i..i + 2)
+            .and_then(|c| u8::from_str_radix(c, 16).ok())
+            .ok_or_else(invalid)
+    };
+    match hex.len() {
+        6 => Ok(Color::from_rgba(channel(0)?, channel(2)?, channel(4)?, 255)),
+        8 => Ok(Color::from_rgba(
+            channel(2)?,
+            channel(4)?,
+            channel(6)?,
+            channel(0)?,
+        )),
+        0 => Ok(Color::new(0.0, 0.0, 0.0, 0.0)),
+        _ => Err(invalid()),
+    }
+}
+
+mod tmx {
+
+    use super::{xml::Element, *};
+
+    fn attribute<T: std::str::FromStr>(
+        element: &Element,
+        name: &str,
+        default: Option<T>,
+    ) -> Result<T, ParseError> {
+        match element.attribute(name) {
+            Some(value) => value.parse().map_err(|_| {
+                ParseError::new(
+                    FORMAT,
+                    format!("invalid {} \"{}\" on <{}>", name, value, element.name),
+                )
+            }),
+            None => default.ok_or_else(|| {
+                ParseError::new(FORMAT, format!("missing {} on <{}>", name, element.name))
+            }),
+        }
+    }
+
+    fn text(element: &Element, name: &str) -> String {
+        element.attribute(name).unwrap_or_default().to_owned()
+    }
+
+    /// Tiled has used both `type` and `class` for the class of tiles and objects.
+    fn class(element: &Element) -> String {
+        element
+            .attribute("class")
+            .or_else(|| element.attribute("type"))
+            .unwrap_or_default()
+            .to_owned()
+    }
+
+    fn flag(element: &Element, name: &str) -> Result<bool, ParseError> {
+        Ok(attribute::<u8>(element, name, Some(1))? != 0)
+    }
+
+    pub fn map(root: &Element) -> Result<Tilemap, ParseError> {
+        if root.name != "map" {
+            return Err(ParseError::new(FORMAT, "expected <map>"));
+        }
+        check_map(
+            root.attribute("orientation").unwrap_or("orthogonal"),
+            attribute::<u8>(root, "infinite", Some(0))? != 0,
+        )?;
+
+        let mut map = Tilemap::new(
+            attribute(root, "width", None)?,
+            attribute(root, "height", None)?,
+            attribute(root, "tilewidth", None)?,
+            attribute(root, "tileheight", None)?,
+        );
+        map.properties = properties(root)?;
+
+        for element in root.children("tileset") {
+            let first_gid = attribute(element, "firstgid", Some(1))?;
+            map.tilesets.push(match element.attribute("source") {
+                Some(source) => external_tileset(source.to_owned(), first_gid),
+                None => tileset(element, first_gid)?,
+            });
+        }
+
+        layers(root, Group::default(), &mut map.layers)?;
+
+        Ok(map)
+    }
+
+    pub fn tileset(element: &Element, first_gid: u32) -> Result<Tileset, ParseError> {
+        if element.name != "tileset" {
+            return Err(ParseError::new(FORMAT, "expected <tileset>"));
+        }
+        let mut tileset = Tileset::new(
+            text(element, "name"),
+            first_gid,
+            attribute(element, "tilewidth", None)?,
+            attribute(element, "tileheight", None)?,
+        );
+        tileset.spacing = attribute(element, "spacing", Some(0.0))?;
+        tileset.margin = attribute(element, "margin", Some(0.0))?;
+        tileset.columns = attribute(element, "columns", Some(0))?;
+        tileset.tile_count = attribute(element, "tilecount", Some(0))?;
+        tileset.image = element
+            .child("image")
+            .and_then(|image| image.attribute("source"))
+            .map(str::to_owned);
+        tileset.properties = properties(element)?;
+
+        for tile in element.children("tile") {
+            let data = TileData {
+                class: class(tile),
+                properties: properties(tile)?,
+                animation: tile
+                    .child("animation")
+                    .map(|animation| {
+                        animation
+                            .children("frame")
+                            .map(|frame| {
+                                Ok(TileFrame {
+                                    tile: attribute(frame, "tileid", None)?,
+                                    duration: attribute::<f32>(frame, "duration", None)? / 1000.0,
+                                })
+                            })
+                            .collect::<Result<_, ParseError>>()
+                    })
+                    .transpose()?
+                    .unwrap_or_default(),
+                collision: tile
+                    .child("objectgroup")
+                    .map(|group| {
+                        group
+                            .children("object")
+                            .map(|shape| object(shape).map(|shape| shape.bounds()))
+                            .collect::<Result<_, ParseError>>()
+                    })
+                    .transpose()?
+                    .unwrap_or_default(),
+            };
+            tileset.tiles.insert(attribute(tile, "id", None)?, data);
+        }
+
+        Ok(tileset)
+    }
+
+    fn layers(parent: &Element, group: Group, output: &mut Vec<Layer>) -> Result<(), ParseError> {
+        for element in parent.elements() {
+            let mut layer = group.layer(
+                text(element, "name"),
+                flag(element, "visible")?,
+                attribute(element, "opacity", Some(1.0))?,
+                vec2(
+                    attribute(element, "offsetx", Some(0.0))?,
+                    attribute(element, "offsety", Some(0.0))?,
+                ),
+            );
+            layer.kind = match element.name.as_str() {
+                "layer" => LayerKind::Tiles(tiles(element)?),
+                "objectgroup" => LayerKind::Objects(
+                    element
+                        .children("object")
+                        .map(object)
+                        .collect::<Result<_, _>>()?,
+                ),
+                "group" => {
+                    let group = Group {
+                        visible: layer.visible,
+                        opacity: layer.opacity,
+                        offset: layer.offset,
+                    };
+                    layers(element, group, output)?;
+                    continue;
+                }
+                _ => continue,
+            };
+            layer.properties = properties(element)?;
+            output.push(layer);
+        }
+        Ok(())
+    }
+
+    fn tiles(element: &Element) -> Result<TileLayer, ParseError> {
+        let width = attribute(element, "width", None)?;
+        let height = attribute(element, "height", None)?;
+        let data = element
+            .child("data")
+            .ok_or_else(|| ParseError::new(FORMAT, "missing <data> on <layer>"))?;
+        let tiles = match data.attribute("encoding") {
+            Some("csv") => csv_tiles(&data.text)?,
+            Some("base64") => {
+                decode_tiles(&data.text, data.attribute("compression"), width, height)?
+            }
+            None => data
+                .children("tile")
+                .map(|tile| attribute(tile, "gid", Some(0)))
+                .collect::<Result<_, _>>()?,
+            Some(other) => {
+                return Err(ParseError::new(
+                    FORMAT,
+                    format!("unsupported encoding \"{}\"", other),
+                ))
+            }
+        };
+        tile_layer(width, height, tiles)
+    }
+
+    fn object(element: &Element) -> Result<MapObject, ParseError> {
+        let points = |name: &str| -> Result<Option<Vec<Vec2>>, ParseError> {
+            element
+                .child(name)
+                .map(|shape| {
+                    text(shape, "points")
+                        .split_whitespace()
+                        .map(|point| {
+                            point
+                                .split_once(',')
+                                .and_then(|(x, y)| Some(vec2(x.parse().ok()?, y.parse().ok()?)))
+                                .ok_or_else(|| {
+                                    ParseError::new(FORMAT, format!("invalid point \"{}\"", point))
+                                })
+                        })
+                        .collect()
+                })
+                .transpose()
+        };
+
+        let shape = if let Some(points) = points("polygon")? {
+            ObjectShape::Polygon(points)
+        } else if let Some(points) = points("polyline")? {
+            ObjectShape::Polyline(points)
+        } else if element.child("ellipse").is_some() {
+            ObjectShape::Ellipse
+        } else if element.child("point").is_some() {
+            ObjectShape::Point
+        } else {
+            ObjectShape::Rectangle
+        };
+
+        Ok(MapObject {
+            id: attribute(element, "id", Some(0))?,
+            name: text(element, "name"),
+            class: class(element),
+            position: vec2(
+                attribute(element, "x", Some(0.0))?,
+                attribute(element, "y", Some(0.0))?,
+            ),
+            size: vec2(
+                attribute(element, "width", Some(0.0))?,
+                attribute(element, "height", Some(0.0))?,
+            ),
+            rotation: attribute::<f32>(element, "rotation", Some(0.0))?.to_radians(),
+            visible: flag(element, "visible")?,
+            tile: Tile::from_raw(attribute(element, "gid", Some(0))?),
+            shape,
+            properties: properties(element)?,
+        })
+    }
+
+    fn properties(element: &Element) -> Result<Properties, ParseError> {
+        let mut properties = Properties::default();
+        for property in element
+            .child("properties")
+            .into_iter()
+            .flat_map(|properties| properties.children("property"))
+        {
+            // long strings are kept in the element's text
+            let value = property.attribute("value").unwrap_or(&property.text);
+            let value = match property.attribute("type").unwrap_or("string") {
+                "bool" => Property::Bool(value == "true"),
+                "int" => Property::Int(attribute(property, "value", Some(0))?),
+                "float" => Property::Float(attribute(property, "value", Some(0.0))?),
+                "color" => Property::Color(color(value)?),
+                "file" => Property::File(value.to_owned()),
+                "object" => Property::Object(attribute(property, "value", Some(0))?),
+                "class" => continue,
+                _ => Property::String(value.to_owned()),
+            };
+            properties.insert(text(property, "name"), value);
+        }
+        Ok(properties)
+    }
+}
+
+mod tmj {
+
+    use super::*;
+
+    #[derive(Deserialize)]
+    struct Map {
+        width: u32,
+        height: u32,
+        tilewidth: f32,
+        tileheight: f32,
+        #[serde(default = "orthogonal")]
+        orientation: String,
+        #[serde(default)]
+        infinite: bool,
+        #[serde(default)]
+        layers: Vec<JsonLayer>,
+        #[serde(default)]
+        tilesets: Vec<JsonTileset>,
+        #[serde(default)]
+        properties: Vec<JsonProperty>,
+    }
+
+    #[derive(Deserialize)]
+    struct JsonLayer {
+        #[serde(rename = "type")]
+        kind: String,
+        #[serde(default)]
+        name: String,
+        #[serde(default = "yes")]
+        visible: bool,
+        #[serde(default = "one")]
+        opacity: f32,
+        #[serde(default)]
+        offsetx: f32,
+        #[serde(default)]
+        offsety: f32,
+        #[serde(default)]
+        width: u32,
+        #[serde(default)]
+        height: u32,
+        #[serde(default)]
+        data: Option<JsonData>,
+        #[serde(default)]
+        compression: Option<String>,
+        #[serde(default)]
+        objects: Vec<JsonObject>,
+        #[serde(default)]
+        layers: Vec<JsonLayer>,
+        #[serde(default)]
+        properties: Vec<JsonProperty>,
+    }
+
+    /// Tile ids written as an array, or base64 encoded.
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum JsonData {
+        Array(Vec<u32>),
+        Base64(String),
+    }
+
+    #[derive(Deserialize)]
+    pub(super) struct JsonTileset {
+        #[serde(default)]
+        firstgid: Option<u32>,
+        #[serde(default)]
+        source: Option<String>,
+        #[serde(default)]
+        name: String,
+        #[serde(default)]
+        tilewidth: f32,
+        #[serde(default)]
+        tileheight: f32,
+        #[serde(default)]
+        spacing: f32,
+        #[serde(default)]
+        margin: f32,
+        #[serde(default)]
+        tilecount: u32,
+        #[serde(default)]
+        columns: u32,
+        #[serde(default)]
+        image: Option<String>,
+        #[serde(default)]
+        tiles: Vec<JsonTile>,
+        #[serde(default)]
+        properties: Vec<JsonProperty>,
+    }
+
+    #[derive(Deserialize)]
+    struct JsonTile {
+        id: u32,
+        #[serde(default, alias = "type")]
+        class: String,
+        #[serde(default)]
+        properties: Vec<JsonProperty>,
+        #[serde(default)]
+        animation: Vec<JsonFrame>,
+        #[serde(default)]
+        objectgroup: Option<JsonObjectGroup>,
+    }
+
+    #[derive(Deserialize)]
+    struct JsonFrame {
+        tileid: u32,
+        duration: f32,
+    }
+
+    #[derive(Deserialize)]
+    struct JsonObjectGroup {
+        #[serde(default)]
+        objects: Vec<JsonObject>,
+    }
+
+    #[derive(Deserialize)]
+    struct JsonObject {
+        #[serde(default)]
+        id: u32,
+        #[serde(default)]
+        name: String,
+        #[serde(default, alias = "type")]
+        class: String,
+        #[serde(default)]
+        x: f32,
+        #[serde(default)]
+        y: f32,
+        #[serde(default)]
+        width: f32,
+        #[serde(default)]
+        height: f32,
+        #[serde(default)]
+        rotation: f32,
+        #[serde(default = "yes")]
+        visible: bool,
+        #[serde(default)]
+        gid: Option<u32>,
+        #[serde(default)]
+        ellipse: bool,
+        #[serde(default)]
+        point: bool,
+        #[serde(default)]
+        polygon: Option<Vec<JsonPoint>>,
+        #[serde(default)]
+        polyline: Option<Vec<JsonPoint>>,
+        #[serde(default)]
+        properties: Vec<JsonProperty>,
+    }
+
+    #[derive(Deserialize)]
+    struct JsonPoint {
+        x: f32,
+        y: f32,
+    }
+
+    #[derive(Deserialize)]
+    struct JsonProperty {
+        name: String,
+        #[serde(rename = "type", default)]
+        kind: String,
+        value: serde_json::Value,
+    }
+
+    fn orthogonal() -> String {
+        "orthogonal".to_owned()
+    }
+
+    fn yes() -> bool {
+        true
+    }
+
+    fn one() -> f32 {
+        1.0
+    }
+
+    pub fn map(data: &str) -> Result<Tilemap, ParseError> {
+        let json: Map =
+            serde_json::from_str(data).map_err(|err| ParseError::new(FORMAT, err.to_string()))?;
+        check_map(&json.orientation, json.infinite)?;
+
+        let mut map = Tilemap::new(json.width, json.height, json.tilewidth, json.tileheight);
+        map.properties = properties(json.properties)?;
+
+        for entry in json.tilesets {
+            let first_gid = entry.firstgid.unwrap_or(1);
+            map.tilesets.push(match entry.source {
+                Some(source) => external_tileset(source, first_gid),
+                None => tileset(entry, first_gid)?,
+            });
+        }
+
+        layers(json.layers, Group::default(), &mut map.layers)?;
+
+        Ok(map)
+    }
+
+    pub(super) fn tileset(json: JsonTileset, first_gid: u32) -> Result<Tileset, ParseError> {
+        let mut tileset = Tileset::new(json.name, first_gid, json.tilewidth, json.tileheight);
+        tileset.spacing = json.spacing;
+        tileset.margin = json.margin;
+        tileset.columns = json.columns;
+        tileset.tile_count = json.tilecount;
+        tileset.image = json.image;
+        tileset.properties = properties(json.properties)?;
+
+        for tile in json.tiles {
+            let data = TileData {
+                class: tile.class,
+                properties: properties(tile.properties)?,
+                animation: tile
+                    .animation
+                    .into_iter()
+                    .map(|frame| TileFrame {
+                        tile: frame.tileid,
+                        duration: frame.duration / 1000.0,
+                    })
+                    .collect(),
+                collision: tile
+                    .objectgroup
+                    .map(|group| {
+                        group
+                            .objects
+                            .into_iter()
+                            .map(|shape| object(shape).map(|shape| shape.bounds()))
+                            .collect::<Result<_, ParseError>>()
+                    })
+                    .transpose()?
+                    .unwrap_or_default(),
+            };
+            tileset.tiles.insert(tile.id, data);
+        }
+
+        Ok(tileset)
+    }
+
+    fn layers(
+        json: Vec<JsonLayer>,
+        group: Group,
+        output: &mut Vec<Layer>,
+    ) -> Result<(), ParseError> {
+        for json in json {
+            let mut layer = group.layer(
+                json.name,
+                json.visible,
+                json.opacity,
+                vec2(json.offsetx, json.offsety),
+            );
+            layer.kind = match json.kind.as_str() {
+                "tilelayer" => {
+                    let tiles = match json.data {
+                        Some(JsonData::Array(tiles)) => tiles,
+                        Some(JsonData::Base64(data)) => decode_tiles(
+                            &data,
+                            json.compression.as_deref(),
+                            json.width,
+                            json.height,
+                        )?,
+                        None => return Err(ParseError::new(FORMAT, "missing data on tile layer")),
+                    };
+                    LayerKind::Tiles(tile_layer(json.width, json.height, tiles)?)
+                }
+                "objectgroup" => LayerKind::Objects(
+                    json.objects
+                        .into_iter()
+                        .map(object)
+                        .collect::<Result<_, _>>()?,
+                ),
+                "group" => {
+                    let group = Group {
+                        visible: layer.visible,
+                        opacity: layer.opacity,
+                        offset: layer.offset,
+                    };
+                    layers(json.layers, group, output)?;
+                    continue;
+                }
+                _ => continue,
+            };
+            layer.properties = properties(json.properties)?;
+            output.push(layer);
+        }
+        Ok(())
+    }
+
+    fn object(json: JsonObject) -> Result<MapObject, ParseError> {
+        let points = |points: Vec<JsonPoint>| {
+            points
+                .into_iter()
+                .map(|point| vec2(point.x, point.y))
+                .collect()
+        };
+        let shape = if let Some(polygon) = json.polygon {
+            ObjectShape::Polygon(points(polygon))
+        } else if let Some(polyline) = json.polyline {
+            ObjectShape::Polyline(points(polyline))
+        } else if json.ellipse {
+            ObjectShape::Ellipse
+        } else if json.point {
+            ObjectShape::Point
+        } else {
+            ObjectShape::Rectangle
+        };
+
+        Ok(MapObject {
+            id: json.id,
+            name: json.name,
+            class: json.class,
+            position: vec2(json.x, json.y),
+            size: vec2(json.width, json.height),
+            rotation: json.rotation.to_radians(),
+            visible: json.visible,
+            tile: json.gid.and_then(Tile::from_raw),
+            shape,
+            properties: properties(json.properties)?,
+        })
+    }
+
+    fn properties(json: Vec<JsonProperty>) -> Result<Properties, ParseError> {
+        let mut properties = Properties::default();
+        for property in json {
+            let invalid = || {
+                ParseError::new(
+                    FORMAT,
+                    format!("invalid value for property \"{}\"", property.name),
+                )
+            };
+            let value = &property.value;
+            let value = match property.kind.as_str() {
+                "bool" => Property::Bool(value.as_bool().ok_or_else(invalid)?),
+                "int" => Property::Int(value.as_i64().ok_or_else(invalid)?),
+                "float" => Property::Float(value.as_f64().ok_or_else(invalid)?),
+                "color" => Property::Color(color(value.as_str().ok_or_else(invalid)?)?),
+                "file" => Property::File(value.as_str().ok_or_else(invalid)?.to_owned()),
+                "object" => Property::Object(value.as_u64().ok_or_else(invalid)? as _),
+                "class" => continue,
+                _ => Property::String(value.as_str().ok_or_else(invalid)?.to_owned()),
+            };
+            properties.insert(property.name, value);
+        }
+        Ok(properties)
+    }
+}
+
+/// A tree of the elements in an XML file.
+mod xml {
+
+    use quick_xml::events::{BytesStart, Event};
+
+    use super::{ParseError, FORMAT};
+
+    /// Deepest elements can be nested, which keeps reading and dropping the tree
+    /// from running out of stack.
+    const MAX_DEPTH: usize = 256;
+
+    #[derive(Debug, Default)]
+    pub struct Element {
+        pub name: String,
+        attributes: Vec<(String, String)>,
+        children: Vec<Element>,
+        pub text: String,
+    }
+
+    impl Element {
+        pub fn attribute(&self, name: &str) -> Option<&str> {
+            self.attributes
+                .iter()
+                .find(|(key, ..)| key == name)
+                .map(|(.., value)| value.as_str())
+        }
+
+        pub fn elements(&self) -> impl Iterator<Item = &Element> {
+            self.children.iter()
+        }
+
+        pub fn children<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a Element> {
+            self.children.iter().filter(move |child| child.name == name)
+        }
+
+        pub fn child(&self, name: &str) -> Option<&Element> {
+            self.children.iter().find(|child| child.name == name)
+        }
+    }
+
+    /// Reads the root element of an XML file.
+    pub fn parse(data: &str) -> Result<Element, ParseError> {
+        let mut reader = quick_xml::Reader::from_str(data);
+        let error = |position: usize, message: &dyn std::fmt::Display| {
+            let line = data.as_bytes()[..position.min(data.len())]
+                .iter()
+                .filter(|byte| **byte == b'\n')
+                .count()
+                + 1;
+            ParseError::new(FORMAT, format!("{} on line {}", message, line))
+        };
+
+        // open elements, with the innermost last
+        let mut open: Vec<Element> = Vec::new();
+
+        loop {
+            let position = reader.buffer_position();
+            let event = reader.read_event().map_err(|err| error(position, &err))?;
+            let closed = match event {
+                Event::Start(..) if open.len() >= MAX_DEPTH => {
+                    return Err(error(position, &"elements are nested too deeply"))
+                }
+                Event::Start(start) => {
+                    open.push(element(&start).map_err(|err| error(position, &err))?);
+                    None
+                }
+                Event::Empty(start) => Some(element(&start).map_err(|err| error(position, &err))?),
+                Event::End(..) => open.pop(),
+                Event::Text(text) => {
+                    if let Some(parent) = open.last_mut() {
+                        let text = text.unescape().map_err(|err| error(position, &err))?;
+                        parent.text.push_str(&text);
+                    }
+                    None
+                }
+                Event::CData(text) => {
+                    if let Some(parent) = open.last_mut() {
+                        let text =
+                            std::str::from_utf8(&text).map_err(|err| error(position, &err))?;
+                        parent.text.push_str(text);
+                    }
+                    None
+                }
+                Event::Eof => return Err(error(position, &"expected an element")),
+                // declarations, comments, doctypes and processing instructions
+                _ => None,
+            };
+            if let Some(closed) = closed {
+                match open.last_mut() {
+                    Some(parent) => parent.children.push(closed),
+                    None => return Ok(closed),
+                }
+            }
+        }
+    }
+
+    fn element(start: &BytesStart) -> Result<Element, quick_xml::Error> {
+        let name = |name: &[u8]| String::from_utf8_lossy(name).into_owned();
+        Ok(Element {
+            name: name(start.name().as_ref()),
+            attributes: start
+                .attributes()
+                .map(|attribute| {
+                    let attribute = attribute?;
+                    Ok((
+                        name(attribute.key.as_ref()),
+                        attribute.unescape_value()?.into_owned(),
+                    ))
+                })
+                .collect::<Result<_, quick_xml::Error>>()?,
+            ..Default::default()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    /// Tiles of every layer in the samples, with a tile flipped each way.
+    const TILES: [u32; 6] = [1, 2, 0x8000_0003, 0x4000_0001, 0x2000_0002, 0];
+
+    fn tmx(data: &str) -> String {
+        format!(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<map version="1.10" orientation="orthogonal" width="3" height="2" tilewidth="16" tileheight="16" infinite="0">
+ <tileset firstgid="1" name="tiles" tilewidth="16" tileheight="16" tilecount="4" columns="2">
+  <image source="tiles.png" width="32" height="32"/>
+ </tileset>
+ <layer id="1" name="ground" width="3" height="2">{}</layer>
+</map>"#,
+            data
+        )
+    }
+
+    fn tmj(data: &str) -> String {
+        format!(
+            r#"{{"width":3,"height":2,"tilewidth":16,"tileheight":16,"orientation":"orthogonal","infinite":false,
+"tilesets":[{{"firstgid":1,"name":"tiles","tilewidth":16,"tileheight":16,"tilecount":4,"columns":2,"image":"tiles.png"}}],
+"layers":[{{"type":"tilelayer","name":"ground","width":3,"height":2,{}}}]}}"#,
+            data
+        )
+    }
+
+    fn assert_tiles(map: &Tilemap) {
+        let expected = TILES
+            .iter()
+            .copied()
+            .map(Tile::from_raw)
+            .collect::<Vec<_>>();
+        match &map.layers[0].kind {
+            LayerKind::Tiles(layer) => assert_eq!(layer.tiles, expected),
+            LayerKind::Objects(..) => panic!("expected a tile layer"),
+        }
+    }
+
+    #[test]
+    fn tmx_encodings() {
+        let tiles = TILES
+            .iter()
+            .map(|gid| format!(r#"<tile gid="{}"/>"#, gid))
+            .collect::<String>();
+        for data in [
+            format!("<data>{}</data>", tiles),
+            r#"<data encoding="csv">
+1,2,2147483651,
+1073741825,536870914,0
+</data>"#
+                .to_owned(),
+            r#"<data encoding="base64">AQAAAAIAAAADAACAAQAAQAIAACAAAAAA</data>"#.to_owned(),
+            r#"<data encoding="base64" compression="zlib">
+   eJxjZGBgYAJiZgaGBkYGBgcgWwHIZQAACgQA6g==
+</data>"#
+                .to_owned(),
+            r#"<data encoding="base64" compression="gzip">H4sIAAAAAAACA2NkYGBgAmJmBoYGRgYGByBbAchlAAB/L1unGAAAAA==</data>"#.to_owned(),
+        ] {
+            assert_tiles(&Tilemap::tmx(&tmx(&data)).unwrap());
+        }
+    }
+
+    #[test]
+    fn tmj_encodings() {
+        for data in [
+            r#""data":[1,2,2147483651,1073741825,536870914,0]"#,
+            r#""encoding":"base64","data":"AQAAAAIAAAADAACAAQAAQAIAACAAAAAA""#,
+            r#""encoding":"base64","compression":"zlib","data":"eJxjZGBgYAJiZgaGBkYGBgcgWwHIZQAACgQA6g==""#,
+            r#""encoding":"base64","compression":"gzip","data":"H4sIAAAAAAACA2NkYGBgAmJmBoYGRgYGByBbAchlAAB/L1unGAAAAA==""#,
+        ] {
+            assert_tiles(&Tilemap::tmj(&tmj(data)).unwrap());
+        }
+    }
+
+    #[test]
+    fn flip_flags() {
+        let map = Tilemap::tmx(&tmx(
+            r#"<data encoding="csv">1,2,2147483651,1073741825,536870914,0</data>"#,
+        ))
+        .unwrap();
+        let tile = |x, y| map.tile(0, x, y).unwrap();
+        assert_eq!((tile(0, 0).gid, tile(0, 0).flip_x), (1, false));
+        assert_eq!((tile(2, 0).gid, tile(2, 0).flip_x), (3, true));
+        assert_eq!((tile(0, 1).gid, tile(0, 1).flip_y), (1, true));
+        assert_eq!((tile(1, 1).gid, tile(1, 1).flip_diagonal), (2, true));
+        assert_eq!(map.tile(0, 2, 1), None);
+        assert_eq!(map.tile(0, 3, 0), None);
+    }
+
+    #[test]
+    fn external_tileset() {
+        let data = r#"<map orientation="orthogonal" width="1" height="1" tilewidth="8" tileheight="8">
+ <tileset firstgid="1" source="tilesets/walls.tsx"/>
+ <layer name="walls" width="1" height="1"><data encoding="csv">1</data></layer>
+</map>"#;
+        let mut map = Tilemap::tmx(data).unwrap();
+        assert_eq!(map.external_tilesets(), vec!["tilesets/walls.tsx"]);
+        assert!(!map.tilesets[0].is_loaded());
+
+        let tileset = r#"<tileset name="walls" tilewidth="8" tileheight="8" tilecount="1" columns="1">
+ <image source="walls.png" width="8" height="8"/>
+</tileset>"#;
+        map.resolve_tileset("tilesets/walls.tsx", tileset).unwrap();
+        assert!(map.external_tilesets().is_empty());
+        assert!(map.tilesets[0].is_loaded());
+        assert_eq!(map.tilesets[0].first_gid, 1);
+        assert_eq!(map.tilesets[0].tile_width, 8.0);
+        assert_eq!(
+            map.tilesets[0].image.as_deref(),
+            Some(
+                std::path::Path::new("tilesets")
+                    .join("walls.png")
+                    .to_str()
+                    .unwrap()
+            )
+        );
+    }
+
+    #[test]
+    fn tsj() {
+        let tileset = Tileset::tsj(
+            r#"{"name":"items","tilewidth":8,"tileheight":16,"tilecount":2,"columns":2,"image":"items.png"}"#,
+            5,
+        )
+        .unwrap();
+        assert_eq!(tileset.first_gid, 5);
+        assert_eq!(tileset.tile_height, 16.0);
+        assert!(tileset.contains(6));
+        assert!(!tileset.contains(7));
+    }
+
+    #[test]
+    fn malformed() {
+        for data in [
+            r#"<data encoding="csv">1,2,3</data>"#,
+            r#"<data encoding="csv">1,2,x,4,5,6</data>"#,
+            r#"<data encoding="base64">AQAA*AAA</data>"#,
+            r#"<data encoding="base64" compression="zlib">AQAAAAIAAAADAACAAQAAQAIAACAAAAAA</data>"#,
+            r#"<data encoding="base64" compression="gzip">AQAAAAIAAAADAACAAQAAQAIAACAAAAAA</data>"#,
+            r#"<data encoding="base64" compression="zstd">AQAAAAIAAAADAACAAQAAQAIAACAAAAAA</data>"#,
+            r#"<data encoding="hex">00</data>"#,
+            r#"<data encoding="csv">1,2,3,4,5,6"#,
+        ] {
+            assert!(Tilemap::tmx(&tmx(data)).is_err(), "{}", data);
+        }
+
+        assert!(Tilemap::tmj(&tmj(r#""data":[1,2]"#)).is_err());
+        assert!(Tilemap::tmj(&tmj(
+            r#""encoding":"base64","compression":"gzip","data":"H4sI""#
+        ))
+        .is_err());
+        assert!(Tilemap::tmj("{").is_err());
+        assert!(Tilemap::tmx(
+            r#"<map orientation="isometric" width="1" height="1" tilewidth="8" tileheight="8"/>"#
+        )
+        .is_err());
+        assert!(Tilemap::tmx(r#"<map orientation="orthogonal" infinite="1" width="1" height="1" tilewidth="8" tileheight="8"/>"#).is_err());
+        assert!(tile_layer(u32::MAX, u32::MAX, Vec::new()).is_err());
+    }
+
+    #[test]
+    fn xml() {
+        let root = xml::parse(
+            r#"<?xml version="1.0"?>
+<!DOCTYPE map>
+<!-- comment -->
+<map name="a &amp; b &#x41;&#66;" quote='"'>
+ <!-- inside -->
+ <text>1 &lt; 2</text>
+ <text><![CDATA[<not a tag>]]></text>
+ <empty/>
+</map>"#,
+        )
+        .unwrap();
+        assert_eq!(root.name, "map");
+        assert_eq!(root.attribute("name"), Some("a & b AB"));
+        assert_eq!(root.attribute("quote"), Some("\""));
+        let texts = root
+            .children("text")
+            .map(|text| text.text.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(texts, ["1 < 2", "<not a tag>"]);
+        assert!(root.child("empty").is_some());
+        assert_eq!(root.elements().count(), 3);
+    }
+
+    #[test]
+    fn xml_rejects() {
+        for data in [
+            "",
+            "<!-- only a comment -->",
+            "<map>",
+            "<map><layer></map>",
+            "<map></layer>",
+            r#"<map name="a></map>"#,
+            "<map name=a></map>",
+            "<map>&unknown;</map>",
+        ] {
+            assert!(xml::parse(data).is_err(), "{}", data);
+        }
+    }
+
+    #[test]
+    fn deeply_nested_xml() {
+        let nested = |depth| format!("{}{}", "<a>".repeat(depth), "</a>".repeat(depth));
+        assert!(xml::parse(&nested(100)).is_ok());
+        assert!(xml::parse(&nested(100_000)).is_err());
+    }
+
+    #[test]
+    fn compressed_data_is_limited() {
+        use base64::Engine;
+        use std::io::Write;
+
+        let mut encoder = flate2::write::ZlibEncoder::new(Vec::new(), Default::default());
+        encoder.write_all(&vec![0; 1 << 20]).unwrap();
+        let data = base64::engine::general_purpose::STANDARD.encode(encoder.finish().unwrap());
+        assert!(decode_tiles(&data, Some("zlib"), 3, 2).unwrap().len() <= 7);
+        assert!(Tilemap::tmx(&tmx(&format!(
+            r#"<data encoding="base64" compression="zlib">{}</data>"#,
+            data
+        )))
+        .is_err());
+    }
+}
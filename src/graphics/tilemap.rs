@@ -0,0 +1,627 @@
+use crate::{
+    context::Context,
+    error::ParseError,
+    math::{vec2, Rectangle, Vec2},
+    utils::HashMap,
+};
+
+use super::{Color, DrawParams, Image, Texture};
+
+/// Custom properties set on maps, layers, tiles and objects.
+pub type Properties = HashMap<String, Property>;
+
+/// An orthogonal map made of tile layers and object layers.
+///
+/// Maps made in Tiled can be loaded with the `tiled` feature.
+#[derive(Debug, Clone)]
+pub struct Tilemap {
+    /// Size of the map in tiles.
+    pub width: u32,
+    pub height: u32,
+    /// Size of a tile in pixels.
+    pub tile_width: f32,
+    pub tile_height: f32,
+    pub tilesets: Vec<Tileset>,
+    pub layers: Vec<Layer>,
+    pub properties: Properties,
+    time: f32,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Property {
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    String(String),
+    Color(Color),
+    /// Path to a file, relative to the map.
+    File(String),
+    /// Id of an object on the map.
+    Object(u32),
+}
+
+/// Tiles cut from a single image.
+#[derive(Debug, Clone)]
+pub struct Tileset {
+    pub name: String,
+    /// Global id of the first tile in the tileset.
+    pub first_gid: u32,
+    pub tile_width: f32,
+    pub tile_height: f32,
+    pub columns: u32,
+    pub tile_count: u32,
+    /// Space between tiles in the image.
+    pub spacing: f32,
+    /// Space around the tiles at the edges of the image.
+    pub margin: f32,
+    /// Path of the tileset's image, relative to the map.
+    pub image: Option<String>,
+    /// Path of the file the tileset is kept in, if it is not part of the map.
+    pub source: Option<String>,
+    /// Extra information for tiles by their id in the tileset.
+    pub tiles: HashMap<u32, TileData>,
+    pub properties: Properties,
+    /// False for a tileset kept in its own file until it is loaded.
+    pub(crate) loaded: bool,
+    texture: Option<Texture>,
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct TileData {
+    pub class: String,
+    pub properties: Properties,
+    /// Frames the tile cycles through. Empty if the tile is not animated.
+    pub animation: Vec<TileFrame>,
+    /// Collision boxes relative to the tile's top left corner.
+    /// Shapes other than rectangles are stored as their bounds.
+    pub collision: Vec<Rectangle>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TileFrame {
+    /// Id of the tile in the tileset.
+    pub tile: u32,
+    /// Duration of the frame in seconds.
+    pub duration: f32,
+}
+
+/// A tile placed on a tile layer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Tile {
+    /// Global id of the tile across all of the map's tilesets.
+    pub gid: u32,
+    pub flip_x: bool,
+    pub flip_y: bool,
+    /// Swaps the tile's x and y axes, which is applied before the other flips.
+    pub flip_diagonal: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct Layer {
+    pub name: String,
+    pub visible: bool,
+    pub opacity: f32,
+    /// Offset of the layer in pixels.
+    pub offset: Vec2,
+    pub properties: Properties,
+    pub kind: LayerKind,
+}
+
+#[derive(Debug, Clone)]
+pub enum LayerKind {
+    Tiles(TileLayer),
+    Objects(Vec<MapObject>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TileLayer {
+    pub width: u32,
+    pub height: u32,
+    /// Tiles ordered left to right, top to bottom.
+    pub tiles: Vec<Option<Tile>>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MapObject {
+    pub id: u32,
+    pub name: String,
+    pub class: String,
+    pub position: Vec2,
+    pub size: Vec2,
+    /// Rotation in radians.
+    pub rotation: f32,
+    pub visible: bool,
+    /// The tile shown by tile objects.
+    pub tile: Option<Tile>,
+    pub shape: ObjectShape,
+    pub properties: Properties,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ObjectShape {
+    Rectangle,
+    Ellipse,
+    Point,
+    /// Points relative to the object's position.
+    Polygon(Vec<Vec2>),
+    /// Points relative to the object's position.
+    Polyline(Vec<Vec2>),
+}
+
+impl Property {
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Self::Bool(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub fn as_int(&self) -> Option<i64> {
+        match self {
+            Self::Int(value) => Some(*value),
+            Self::Object(value) => Some(*value as _),
+            _ => None,
+        }
+    }
+
+    pub fn as_float(&self) -> Option<f64> {
+        match self {
+            Self::Float(value) => Some(*value),
+            Self::Int(value) => Some(*value as _),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Self::String(value) | Self::File(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn as_color(&self) -> Option<Color> {
+        match self {
+            Self::Color(value) => Some(*value),
+            _ => None,
+        }
+    }
+}
+
+impl Tile {
+    const FLIP_X: u32 = 0x80000000;
+    const FLIP_Y: u32 = 0x40000000;
+    const FLIP_DIAGONAL: u32 = 0x20000000;
+    /// Also removes the flag for rotated hexagonal tiles.
+    const FLAGS: u32 = 0xF0000000;
+
+    pub fn new(gid: u32) -> Self {
+        Self {
+            gid,
+            flip_x: false,
+            flip_y: false,
+            flip_diagonal: false,
+        }
+    }
+
+    /// Reads a global id with flip flags, returning None for empty tiles.
+    pub fn from_raw(raw: u32) -> Option<Self> {
+        let gid = raw & !Self::FLAGS;
+        (gid != 0).then_some(Self {
+            gid,
+            flip_x: raw & Self::FLIP_X != 0,
+            flip_y: raw & Self::FLIP_Y != 0,
+            flip_diagonal: raw & Self::FLIP_DIAGONAL != 0,
+        })
+    }
+
+    /// Draw parameters that flip and rotate the tile's texture as Tiled does.
+    fn params(&self, color: Color, source: Rectangle) -> DrawParams {
+        // a diagonal flip is a quarter turn with the flips swapped and the vertical one inverted
+        let (flip_x, flip_y, rotation) = match self.flip_diagonal {
+            true => (self.flip_y, !self.flip_x, std::f32::consts::FRAC_PI_2),
+            false => (self.flip_x, self.flip_y, 0.0),
+        };
+        DrawParams {
+            color,
+            source: Some(source),
+            rotation,
+            flip_x,
+            flip_y,
+            ..Default::default()
+        }
+    }
+}
+
+impl Tileset {
+    pub fn new(name: impl Into<String>, first_gid: u32, tile_width: f32, tile_height: f32) -> Self {
+        Self {
+            name: name.into(),
+            first_gid,
+            tile_width,
+            tile_height,
+            columns: 0,
+            tile_count: 0,
+            spacing: 0.0,
+            margin: 0.0,
+            image: None,
+            source: None,
+            tiles: HashMap::default(),
+            properties: Properties::default(),
+            loaded: true,
+            texture: None,
+        }
+    }
+
+    /// Whether the tileset's tiles are loaded. Tilesets kept in their own files
+    /// are not loaded until [Tilemap::resolve_tileset] is called.
+    pub fn is_loaded(&self) -> bool {
+        self.loaded
+    }
+
+    /// Whether the global id belongs to a tile in this tileset.
+    pub fn contains(&self, gid: u32) -> bool {
+        gid >= self.first_gid && gid - self.first_gid < self.tile_count
+    }
+
+    /// Area of the tileset's image a tile is in.
+    pub fn source(&self, tile: u32) -> Rectangle {
+        let columns = self.columns.max(1);
+        Rectangle::new(
+            self.margin + (tile % columns) as f32 * (self.tile_width + self.spacing),
+            self.margin + (tile / columns) as f32 * (self.tile_height + self.spacing),
+            self.tile_width,
+            self.tile_height,
+        )
+    }
+
+    pub fn tile(&self, tile: u32) -> Option<&TileData> {
+        self.tiles.get(&tile)
+    }
+
+    pub fn texture(&self) -> Option<&Texture> {
+        self.texture.as_ref()
+    }
+
+    pub fn set_texture(&mut self, texture: Texture) {
+        if self.columns == 0 {
+            let step = |size: f32, tile: f32| {
+                ((size - self.margin * 2.0 + self.spacing) / (tile + self.spacing)).floor() as u32
+            };
+            self.columns = step(texture.width(), self.tile_width);
+            if self.tile_count == 0 {
+                self.tile_count = self.columns * step(texture.height(), self.tile_height);
+            }
+        }
+        self.texture = Some(texture);
+    }
+
+    /// The tile shown at a point in time, following the tile's animation.
+    fn animated(&self, tile: u32, time: f32) -> u32 {
+        let animation = match self.tiles.get(&tile) {
+            Some(data) if !data.animation.is_empty() => &data.animation,
+            _ => return tile,
+        };
+        let duration: f32 = animation.iter().map(|frame| frame.duration).sum();
+        if duration <= 0.0 {
+            return tile;
+        }
+        let mut time = time % duration;
+        for frame in animation {
+            if time < frame.duration {
+                return frame.tile;
+            }
+            time -= frame.duration;
+        }
+        tile
+    }
+}
+
+impl TileLayer {
+    /// Creates an empty layer, failing if it has more tiles than can be stored.
+    pub fn new(width: u32, height: u32) -> Result<Self, ParseError> {
+        let mut tiles = Vec::new();
+        let len = (width as usize)
+            .checked_mul(height as usize)
+            .filter(|len| tiles.try_reserve_exact(*len).is_ok())
+            .ok_or_else(|| {
+                ParseError::new("tile layer", format!("{}x{} is too large", width, height))
+            })?;
+        tiles.resize(len, None);
+        Ok(Self {
+            width,
+            height,
+            tiles,
+        })
+    }
+
+    pub fn get(&self, x: u32, y: u32) -> Option<Tile> {
+        self.index(x, y)
+            .and_then(|index| self.tiles.get(index).copied().flatten())
+    }
+
+    pub fn set(&mut self, x: u32, y: u32, tile: Option<Tile>) {
+        if let Some(slot) = self.index(x, y).and_then(|index| self.tiles.get_mut(index)) {
+            *slot = tile;
+        }
+    }
+
+    fn index(&self, x: u32, y: u32) -> Option<usize> {
+        match x < self.width && y < self.height {
+            true => Some(y as usize * self.width as usize + x as usize),
+            false => None,
+        }
+    }
+}
+
+impl MapObject {
+    /// The area the object covers, ignoring rotation.
+    pub fn bounds(&self) -> Rectangle {
+        let points = match &self.shape {
+            ObjectShape::Polygon(points) | ObjectShape::Polyline(points) => points,
+            _ => return Rectangle::new(self.position.x, self.position.y, self.size.x, self.size.y),
+        };
+        let (min, max) = points.iter().fold(
+            (Vec2::splat(f32::MAX), Vec2::splat(f32::MIN)),
+            |(min, max), point| (min.min(*point), max.max(*point)),
+        );
+        if points.is_empty() {
+            return Rectangle::new(self.position.x, self.position.y, 0.0, 0.0);
+        }
+        Rectangle::new(
+            self.position.x + min.x,
+            self.position.y + min.y,
+            max.x - min.x,
+            max.y - min.y,
+        )
+    }
+}
+
+impl Tilemap {
+    /// Creates an empty map with a size in tiles.
+    pub fn new(width: u32, height: u32, tile_width: f32, tile_height: f32) -> Self {
+        Self {
+            width,
+            height,
+            tile_width,
+            tile_height,
+            tilesets: Vec::new(),
+            layers: Vec::new(),
+            properties: Properties::default(),
+            time: 0.0,
+        }
+    }
+
+    /// Size of the map in pixels.
+    pub fn size(&self) -> Vec2 {
+        vec2(
+            self.width as f32 * self.tile_width,
+            self.height as f32 * self.tile_height,
+        )
+    }
+
+    /// Paths of the images used by the map's tilesets.
+    pub fn images(&self) -> Vec<&str> {
+        let mut images = Vec::new();
+        for image in self
+            .tilesets
+            .iter()
+            .flat_map(|tileset| tileset.image.as_deref())
+        {
+            if !images.contains(&image) {
+                images.push(image);
+            }
+        }
+        images
+    }
+
+    /// Creates the texture of every tileset using the image at the given path.
+    pub fn set_image(&mut self, ctx: &mut Context, path: &str, image: &Image) {
+        let mut texture = None;
+        for tileset in self
+            .tilesets
+            .iter_mut()
+            .filter(|tileset| tileset.image.as_deref() == Some(path))
+        {
+            let texture = texture
+                .get_or_insert_with(|| Texture::from_image(ctx, image))
+                .clone();
+            tileset.set_texture(texture);
+        }
+    }
+
+    /// Advances animated tiles.
+    pub fn update(&mut self, delta: f32) {
+        self.time += delta;
+    }
+
+    pub fn layer(&self, name: &str) -> Option<&Layer> {
+        self.layers.iter().find(|layer| layer.name == name)
+    }
+
+    pub fn layer_mut(&mut self, name: &str) -> Option<&mut Layer> {
+        self.layers.iter_mut().find(|layer| layer.name == name)
+    }
+
+    /// The tileset a tile belongs to, and the id of the tile in it.
+    pub fn tileset(&self, gid: u32) -> Option<(&Tileset, u32)> {
+        self.tilesets
+            .iter()
+            .rev()
+            .find(|tileset| tileset.first_gid <= gid)
+            .map(|tileset| (tileset, gid - tileset.first_gid))
+    }
+
+    pub fn tile_data(&self, gid: u32) -> Option<&TileData> {
+        self.tileset(gid)
+            .and_then(|(tileset, tile)| tileset.tile(tile))
+    }
+
+    pub fn tile_property(&self, gid: u32, name: &str) -> Option<&Property> {
+        self.tile_data(gid)
+            .and_then(|data| data.properties.get(name))
+    }
+
+    /// The tile at a position in tiles on a layer.
+    pub fn tile(&self, layer: usize, x: u32, y: u32) -> Option<Tile> {
+        match &self.layers.get(layer)?.kind {
+            LayerKind::Tiles(tiles) => tiles.get(x, y),
+            LayerKind::Objects(..) => None,
+        }
+    }
+
+    pub fn set_tile(&mut self, layer: usize, x: u32, y: u32, tile: Option<Tile>) {
+        if let Some(LayerKind::Tiles(tiles)) =
+            self.layers.get_mut(layer).map(|layer| &mut layer.kind)
+        {
+            tiles.set(x, y, tile);
+        }
+    }
+
+    /// Position in tiles of the tile containing a point on the map.
+    pub fn tile_position(&self, point: Vec2) -> Option<(u32, u32)> {
+        let x = (point.x / self.tile_width).floor();
+        let y = (point.y / self.tile_height).floor();
+        match x >= 0.0 && y >= 0.0 && x < self.width as f32 && y < self.height as f32 {
+            true => Some((x as u32, y as u32)),
+            false => None,
+        }
+    }
+
+    /// Objects of every object layer.
+    pub fn objects(&self) -> impl Iterator<Item = &MapObject> {
+        self.layers.iter().flat_map(|layer| match &layer.kind {
+            LayerKind::Objects(objects) => objects.as_slice(),
+            LayerKind::Tiles(..) => &[],
+        })
+    }
+
+    pub fn object(&self, name: &str) -> Option<&MapObject> {
+        self.objects().find(|object| object.name == name)
+    }
+
+    /// Collision boxes of tiles on visible tile layers overlapping an area of the map.
+    ///
+    /// Tiles use the collision shapes set on them in their tileset,
+    /// or cover their whole tile if they have the property `collides` set to true.
+    pub fn colliders(&self, area: Rectangle) -> Vec<Rectangle> {
+        let mut colliders = Vec::new();
+        for layer in self.layers.iter().filter(|layer| layer.visible) {
+            let tiles = match &layer.kind {
+                LayerKind::Tiles(tiles) => tiles,
+                LayerKind::Objects(..) => continue,
+            };
+            let (columns, rows) = self.tile_range(tiles, area.offset(-layer.offset));
+            for y in rows {
+                for x in columns.clone() {
+                    let tile = match tiles.get(x, y) {
+                        Some(tile) => tile,
+                        None => continue,
+                    };
+                    let position = vec2(x as f32 * self.tile_width, y as f32 * self.tile_height)
+                        + layer.offset;
+                    let data = self.tile_data(tile.gid);
+                    let boxes = match data {
+                        Some(data) if !data.collision.is_empty() => data.collision.clone(),
+                        _ if self
+                            .tile_property(tile.gid, "collides")
+                            .and_then(Property::as_bool)
+                            .unwrap_or_default() =>
+                        {
+                            vec![Rectangle::new(0.0, 0.0, self.tile_width, self.tile_height)]
+                        }
+                        _ => continue,
+                    };
+                    colliders.extend(
+                        boxes
+                            .into_iter()
+                            .map(|bounds| bounds.offset(position))
+                            .filter(|bounds| bounds.overlaps(&area)),
+                    );
+                }
+            }
+        }
+        colliders
+    }
+
+    /// Whether an area of the map overlaps any of the map's [colliders](Tilemap::colliders).
+    pub fn collides(&self, area: Rectangle) -> bool {
+        !self.colliders(area).is_empty()
+    }
+
+    /// Draws every visible tile layer, skipping tiles outside of the view.
+    pub fn draw(&self, ctx: &mut Context, x: f32, y: f32, color: Color) {
+        for index in 0..self.layers.len() {
+            self.draw_layer(ctx, index, x, y, color);
+        }
+    }
+
+    /// Draws a tile layer if it is visible, skipping tiles outside of the view.
+    pub fn draw_layer(&self, ctx: &mut Context, layer: usize, x: f32, y: f32, color: Color) {
+        let layer = match self.layers.get(layer) {
+            Some(layer) if layer.visible => layer,
+            _ => return,
+        };
+        let tiles = match &layer.kind {
+            LayerKind::Tiles(tiles) => tiles,
+            LayerKind::Objects(..) => return,
+        };
+
+        let origin = vec2(x, y) + layer.offset;
+        let color = Color::new(color.r, color.g, color.b, color.a * layer.opacity);
+
+        // tiles larger than the map's tiles stick out above and to the right of their position
+        let overhang = self.tilesets.iter().fold(Vec2::ZERO, |overhang, tileset| {
+            overhang.max(vec2(
+                tileset.tile_width - self.tile_width,
+                tileset.tile_height - self.tile_height,
+            ))
+        });
+        let view = super::visible_world(ctx);
+        let view = Rectangle::new(
+            view.x - origin.x - overhang.x,
+            view.y - origin.y,
+            view.w + overhang.x,
+            view.h + overhang.y,
+        );
+
+        let (columns, rows) = self.tile_range(tiles, view);
+        for row in rows {
+            for column in columns.clone() {
+                let tile = match tiles.get(column, row) {
+                    Some(tile) => tile,
+                    None => continue,
+                };
+                let (tileset, id) = match self.tileset(tile.gid) {
+                    Some(tileset) => tileset,
+                    None => continue,
+                };
+                if let Some(texture) = &tileset.texture {
+                    let id = tileset.animated(id, self.time);
+                    texture.draw(
+                        ctx,
+                        origin.x + column as f32 * self.tile_width,
+                        origin.y + (row + 1) as f32 * self.tile_height - tileset.tile_height,
+                        tile.params(color, tileset.source(id)),
+                    );
+                }
+            }
+        }
+    }
+
+    /// Columns and rows of the tiles in a layer overlapping an area relative to the layer.
+    fn tile_range(
+        &self,
+        tiles: &TileLayer,
+        area: Rectangle,
+    ) -> (std::ops::Range<u32>, std::ops::Range<u32>) {
+        let range = |start: f32, size: f32, tile: f32, count: u32| {
+            let first = (start / tile).floor().max(0.0) as u32;
+            let last = ((start + size) / tile).ceil().clamp(0.0, count as f32) as u32;
+            first.min(last)..last
+        };
+        (
+            range(area.x, area.w, self.tile_width, tiles.width),
+            range(area.y, area.h, self.tile_height, tiles.height),
+        )
+    }
+}
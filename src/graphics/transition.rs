@@ -0,0 +1,217 @@
+use macroquad::prelude::{get_internal_gl, set_camera, Camera2D, DrawMode, Rect, Vertex};
+use std::f32::consts::{PI, TAU};
+
+use crate::{
+    context::Context,
+    error::EngineError,
+    math::{vec2, Rectangle, Vec2},
+};
+
+use super::{BlendMode, Color, DrawParams, Shader, Texture};
+
+/// An effect covering the switch from one scene to another,
+/// played over the last frame drawn before the switch.
+#[derive(Debug, Clone)]
+pub struct Transition {
+    pub effect: TransitionEffect,
+    /// Length of the transition in seconds.
+    pub duration: f32,
+}
+
+#[derive(Debug, Clone)]
+pub enum TransitionEffect {
+    /// Fades the old scene out to the color, then fades the new scene in from it.
+    Fade(Color),
+    /// Fades the old scene out over the new one.
+    Dissolve,
+    /// Uncovers the new scene with an edge moving across the screen.
+    Wipe(WipeDirection),
+    /// Uncovers the new scene with a circle growing from the center of the screen.
+    Iris,
+    /// Draws the old scene with a shader over the new one.
+    ///
+    /// The shader is given the old scene as its `Texture`
+    /// and must declare a `progress` float uniform, set from 0 to 1 over the transition.
+    Shader(Shader),
+}
+
+/// The direction the edge of a wipe moves in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WipeDirection {
+    Left,
+    Right,
+    Up,
+    Down,
+}
+
+impl Transition {
+    pub fn new(effect: TransitionEffect, duration: f32) -> Self {
+        Self { effect, duration }
+    }
+
+    pub fn fade(color: Color, duration: f32) -> Self {
+        Self::new(TransitionEffect::Fade(color), duration)
+    }
+
+    pub fn dissolve(duration: f32) -> Self {
+        Self::new(TransitionEffect::Dissolve, duration)
+    }
+
+    pub fn wipe(direction: WipeDirection, duration: f32) -> Self {
+        Self::new(TransitionEffect::Wipe(direction), duration)
+    }
+
+    pub fn iris(duration: f32) -> Self {
+        Self::new(TransitionEffect::Iris, duration)
+    }
+
+    pub fn shader(shader: Shader, duration: f32) -> Self {
+        Self::new(TransitionEffect::Shader(shader), duration)
+    }
+}
+
+pub(crate) struct ActiveTransition {
+    transition: Transition,
+    frame: Texture,
+    time: f32,
+}
+
+impl ActiveTransition {
+    fn progress(&self) -> f32 {
+        match self.transition.duration > 0.0 {
+            true => (self.time / self.transition.duration).min(1.0),
+            false => 1.0,
+        }
+    }
+
+    fn draw(&self, ctx: &mut Context, window: Vec2) {
+        let progress = self.progress();
+        let full = Rectangle::new(0.0, 0.0, window.x, window.y);
+        let params = |source: Rectangle, color: Color| DrawParams {
+            color,
+            source: Some(source),
+            dest_size: Some(source.size()),
+            blend: Some(BlendMode::Alpha),
+            ..Default::default()
+        };
+
+        super::apply_blend_mode(ctx, Some(BlendMode::Alpha));
+
+        match &self.transition.effect {
+            TransitionEffect::Fade(color) => {
+                if progress < 0.5 {
+                    self.frame.draw(ctx, 0.0, 0.0, params(full, Color::WHITE));
+                }
+                let alpha = 1.0 - (progress * 2.0 - 1.0).abs();
+                let color = Color::new(color.r, color.g, color.b, color.a * alpha);
+                macroquad::prelude::draw_rectangle(0.0, 0.0, window.x, window.y, color);
+            }
+            TransitionEffect::Dissolve => {
+                let color = Color::new(1.0, 1.0, 1.0, 1.0 - progress);
+                self.frame.draw(ctx, 0.0, 0.0, params(full, color));
+            }
+            TransitionEffect::Wipe(direction) => {
+                let (w, h) = (window.x, window.y);
+                let source = match direction {
+                    WipeDirection::Left => Rectangle::new(0.0, 0.0, w * (1.0 - progress), h),
+                    WipeDirection::Right => {
+                        Rectangle::new(w * progress, 0.0, w * (1.0 - progress), h)
+                    }
+                    WipeDirection::Up => Rectangle::new(0.0, 0.0, w, h * (1.0 - progress)),
+                    WipeDirection::Down => {
+                        Rectangle::new(0.0, h * progress, w, h * (1.0 - progress))
+                    }
+                };
+                self.frame
+                    .draw(ctx, source.x, source.y, params(source, Color::WHITE));
+            }
+            TransitionEffect::Iris => {
+                let (vertices, indices) = iris(window, progress);
+                let gl = unsafe { get_internal_gl() }.quad_gl;
+                gl.texture(Some(**self.frame.data()));
+                gl.draw_mode(DrawMode::Triangles);
+                gl.geometry(&vertices, &indices);
+                gl.texture(None);
+            }
+            TransitionEffect::Shader(shader) => {
+                shader.set_uniform("progress", progress);
+                self.frame
+                    .draw_with_shader(ctx, shader, 0.0, 0.0, params(full, Color::WHITE));
+            }
+        }
+    }
+}
+
+/// Textured band covering the window outside of a circle growing with the progress.
+fn iris(window: Vec2, progress: f32) -> (Vec<Vertex>, Vec<u16>) {
+    let center = window / 2.0;
+    let segments = 64u16;
+    // push the polygon's edges out past the circle, so they still reach the window's corners
+    let outer = center.length() / (PI / segments as f32).cos();
+    let inner = outer * progress;
+
+    let vertex = |radius: f32, angle: f32| {
+        let position = center + vec2(angle.cos(), angle.sin()) * radius;
+        let uv = position / window;
+        Vertex::new(position.x, position.y, 0.0, uv.x, uv.y, Color::WHITE)
+    };
+
+    let vertices = (0..=segments)
+        .flat_map(|i| {
+            let angle = TAU * i as f32 / segments as f32;
+            [vertex(outer, angle), vertex(inner, angle)]
+        })
+        .collect();
+    let indices = (0..segments)
+        .flat_map(|i| {
+            let i = i * 2;
+            [i, i + 1, i + 3, i, i + 3, i + 2]
+        })
+        .collect();
+
+    (vertices, indices)
+}
+
+/// Plays a transition over what has been drawn to the window so far this frame.
+/// Any transition already playing is replaced.
+pub fn start_transition(ctx: &mut Context, transition: Transition) -> Result<(), EngineError> {
+    let frame = super::screenshot(ctx)?;
+    ctx.transition = Some(ActiveTransition {
+        transition,
+        frame: Texture::crate_from_image(&frame.0),
+        time: 0.0,
+    });
+    Ok(())
+}
+
+pub fn is_transitioning(ctx: &Context) -> bool {
+    ctx.transition.is_some()
+}
+
+/// Draws the transition being played over the whole window.
+pub(crate) fn draw_transition(ctx: &mut Context, delta: f32) {
+    let transition = match ctx.transition.take() {
+        Some(transition) => transition,
+        None => return,
+    };
+
+    super::reset_canvas(ctx);
+
+    let window = ctx.scaling.window;
+    set_camera(&Camera2D {
+        viewport: Some((0, 0, window.x as _, window.y as _)),
+        ..Camera2D::from_display_rect(Rect::new(0.0, 0.0, window.x, window.y))
+    });
+
+    transition.draw(ctx, window);
+
+    super::apply_scaling(ctx);
+
+    let transition = ActiveTransition {
+        time: transition.time + delta,
+        ..transition
+    };
+    if transition.progress() < 1.0 {
+        ctx.transition = Some(transition);
+    }
+}
@@ -1,4 +1,9 @@
-use crate::Context;
+use crate::{
+    math::{vec2, Rectangle, Vec2},
+    Context,
+};
+
+use super::Color;
 
 /// Algorithms that can be used to scale the game's screen.
 #[derive(Debug, Copy, Clone, PartialEq)]
@@ -11,19 +16,20 @@ pub enum ScalingMode {
     /// The screen will be stretched to fill the window, without trying to preserve the original
     /// aspect ratio. Distortion/stretching/squashing may occur.
     Stretch,
-    // /// The entire screen will be displayed as large as possible while maintaining the original
-    // /// aspect ratio. Letterboxing may occur.
-    // ShowAll,
 
-    // /// Works the same as ShowAll, but will only scale by integer values.
-    // ShowAllPixelPerfect,
+    /// The entire screen will be displayed as large as possible while maintaining the original
+    /// aspect ratio. Letterboxing may occur.
+    ShowAll,
+
+    /// Works the same as ShowAll, but will only scale by integer values.
+    ShowAllPixelPerfect,
 
-    // /// The screen will fill the entire window, maintaining the original aspect ratio but
-    // /// potentially being cropped.
-    // Crop,
+    /// The screen will fill the entire window, maintaining the original aspect ratio but
+    /// potentially being cropped.
+    Crop,
 
-    // /// Works the same as Crop, but will only scale by integer values.
-    // CropPixelPerfect,
+    /// Works the same as Crop, but will only scale by integer values.
+    CropPixelPerfect,
 }
 
 impl Default for ScalingMode {
@@ -32,20 +38,129 @@ impl Default for ScalingMode {
     }
 }
 
-#[allow(unused_variables)]
+impl ScalingMode {
+    /// Returns the area of the window the screen is drawn to
+    /// for a screen of size `inner` inside a window of size `outer`.
+    pub fn viewport(&self, inner: Vec2, outer: Vec2) -> Rectangle {
+        let scale = match self {
+            ScalingMode::Fixed | ScalingMode::Stretch => {
+                return Rectangle::new(0.0, 0.0, outer.x, outer.y)
+            }
+            ScalingMode::ShowAll => (outer.x / inner.x).min(outer.y / inner.y),
+            ScalingMode::ShowAllPixelPerfect => {
+                let scale = (outer.x / inner.x).min(outer.y / inner.y);
+                // do not scale down to nothing if the window is smaller than the screen
+                if scale < 1.0 {
+                    scale
+                } else {
+                    scale.floor()
+                }
+            }
+            ScalingMode::Crop => (outer.x / inner.x).max(outer.y / inner.y),
+            ScalingMode::CropPixelPerfect => (outer.x / inner.x).max(outer.y / inner.y).ceil(),
+        };
+        let size = inner * scale;
+        let origin = ((outer - size) / 2.0).round();
+        Rectangle::new(origin.x, origin.y, size.x, size.y)
+    }
+}
+
 pub fn set_scaling_mode(ctx: &mut Context, mode: ScalingMode, scale: Option<f32>) {
-    use macroquad::prelude::*;
-    let scaling = scale.unwrap_or(1.0);
-    match mode {
-        ScalingMode::Fixed => set_default_camera(),
-        ScalingMode::Stretch => set_camera(&Camera2D::from_display_rect(Rect::new(
-            0.0,
-            0.0,
-            width(ctx) / scaling,
-            height(ctx) / scaling,
-        ))),
+    ctx.scaling.mode = mode;
+    ctx.scaling.scale = scale;
+    if ctx.canvas.is_none() {
+        apply_scaling(ctx);
     }
-    ctx.scaling = (mode, scale).into();
+}
+
+pub fn scaling_mode(ctx: &Context) -> ScalingMode {
+    ctx.scaling.mode
+}
+
+/// Set the color drawn around the screen when the scaling mode leaves part of the window empty.
+pub fn set_letterbox_color(ctx: &mut Context, color: Color) {
+    ctx.scaling.letterbox = color;
+}
+
+pub fn letterbox_color(ctx: &Context) -> Color {
+    ctx.scaling.letterbox
+}
+
+/// The area of the window the screen is currently drawn to.
+pub fn viewport(ctx: &Context) -> Rectangle {
+    ctx.scaling.viewport
+}
+
+/// Size of the game's screen before it is scaled to fit the window.
+pub fn screen_size(ctx: &Context) -> Vec2 {
+    ctx.scaling.screen
+}
+
+/// Converts a position in the window, such as the mouse position, to a position on the screen.
+pub fn window_to_screen(ctx: &Context, point: Vec2) -> Vec2 {
+    let viewport = ctx.scaling.viewport;
+    (point - viewport.point()) * ctx.scaling.screen / viewport.size()
+}
+
+/// Converts a position on the screen to a position in the window.
+pub fn screen_to_window(ctx: &Context, point: Vec2) -> Vec2 {
+    let viewport = ctx.scaling.viewport;
+    point * viewport.size() / ctx.scaling.screen + viewport.point()
+}
+
+/// Sets the camera used to draw to the screen with the scaling mode and [Camera](super::Camera).
+pub(crate) fn apply_scaling(ctx: &mut Context) {
+    let window = vec2(width(ctx), height(ctx));
+
+    let (screen, viewport) = match ctx.scaling.mode {
+        ScalingMode::Fixed => (window, Rectangle::new(0.0, 0.0, window.x, window.y)),
+        ScalingMode::Stretch => (
+            window / ctx.scaling.scale.unwrap_or(1.0),
+            Rectangle::new(0.0, 0.0, window.x, window.y),
+        ),
+        mode => (ctx.scaling.size, mode.viewport(ctx.scaling.size, window)),
+    };
+
+    ctx.scaling.window = window;
+    ctx.scaling.screen = screen;
+    ctx.scaling.viewport = viewport;
+
+    macroquad::prelude::set_camera(&ctx.camera.to_camera2d(window, screen, viewport));
+}
+
+/// Fills the parts of the window outside of the viewport with the letterbox color.
+pub(crate) fn draw_letterbox(ctx: &mut Context) {
+    use macroquad::prelude::{draw_rectangle, set_camera, Camera2D, Rect};
+
+    super::reset_canvas(ctx);
+    super::apply_blend_mode(ctx, Some(super::BlendMode::Alpha));
+
+    let window = ctx.scaling.window;
+    let viewport = ctx.scaling.viewport;
+
+    if viewport.x <= 0.0 && viewport.y <= 0.0 {
+        return;
+    }
+
+    set_camera(&Camera2D {
+        viewport: Some((0, 0, window.x as _, window.y as _)),
+        ..Camera2D::from_display_rect(Rect::new(0.0, 0.0, window.x, window.y))
+    });
+
+    let color = ctx.scaling.letterbox;
+    let right = viewport.x + viewport.w;
+    let bottom = viewport.y + viewport.h;
+
+    if viewport.x > 0.0 {
+        draw_rectangle(0.0, 0.0, viewport.x, window.y, color);
+        draw_rectangle(right, 0.0, window.x - right, window.y, color);
+    }
+    if viewport.y > 0.0 {
+        draw_rectangle(0.0, 0.0, window.x, viewport.y, color);
+        draw_rectangle(0.0, bottom, window.x, window.y - bottom, color);
+    }
+
+    apply_scaling(ctx);
 }
 
 #[allow(unused_variables)]
@@ -42,6 +42,31 @@ pub trait State<U: UserContext = ()> {
     fn draw(&mut self, ctx: &mut Context, userctx: &mut U) {}
 
     fn end(&mut self, ctx: &mut Context, userctx: &mut U) {}
+
+    /// Called at the end of every frame. Returning a state ends this one and starts the returned one.
+    fn next(&mut self, ctx: &mut Context, userctx: &mut U) -> Option<NextState<U>> {
+        None
+    }
+}
+
+/// A state to switch to, with the transition played over the switch.
+pub struct NextState<U: UserContext = ()> {
+    pub state: Box<dyn State<U>>,
+    pub transition: Option<graphics::Transition>,
+}
+
+impl<U: UserContext> NextState<U> {
+    pub fn new(state: impl State<U> + 'static) -> Self {
+        Self {
+            state: Box::new(state),
+            transition: None,
+        }
+    }
+
+    pub fn with_transition(mut self, transition: graphics::Transition) -> Self {
+        self.transition = Some(transition);
+        self
+    }
 }
 
 pub fn run<
@@ -50,7 +75,7 @@ pub fn run<
     OPENFUNC: Future<Output = OPEN> + 'static,
     LOAD,
     LOADFUNC: FnOnce(&mut Context, &mut U, OPEN) -> LOAD + 'static,
-    S: State<U>,
+    S: State<U> + 'static,
     SFUNC: FnOnce(&mut Context, &mut U, LOAD) -> S + 'static,
 >(
     args: ContextBuilder<impl Into<String>>,
@@ -58,6 +83,8 @@ pub fn run<
     load: LOADFUNC,
     state: SFUNC,
 ) {
+    let size = math::vec2(args.width as _, args.height as _);
+
     macroquad::Window::from_config(args.into(), async move {
         macroquad::prelude::prevent_quit();
 
@@ -66,7 +93,7 @@ pub fn run<
 
         let open = open.await;
 
-        let mut ctx = Context::new()
+        let mut ctx = Context::new(size)
             .unwrap_or_else(|err| panic!("Could not initialize Context with error {}", err));
 
         let mut userctx = U::new(&mut ctx)
@@ -74,7 +101,7 @@ pub fn run<
 
         let data = (load)(&mut ctx, &mut userctx, open);
 
-        let mut state = (state)(&mut ctx, &mut userctx, data);
+        let mut state: Box<dyn State<U>> = Box::new((state)(&mut ctx, &mut userctx, data));
 
         state.start(&mut ctx, &mut userctx);
 
@@ -82,10 +109,33 @@ pub fn run<
 
             ctx.input.update();
 
-            state.update(&mut ctx, &mut userctx, macroquad::prelude::get_frame_time());
+            let delta = macroquad::prelude::get_frame_time();
+
+            state.update(&mut ctx, &mut userctx, delta);
+
+            graphics::update_camera(&mut ctx, delta);
 
             state.draw(&mut ctx, &mut userctx);
 
+            graphics::flush_draw_queue(&mut ctx);
+
+            graphics::draw_letterbox(&mut ctx);
+
+            graphics::draw_transition(&mut ctx, delta);
+
+            graphics::update_screenshot(&mut ctx);
+
+            if let Some(next) = state.next(&mut ctx, &mut userctx) {
+                if let Some(transition) = next.transition {
+                    if let Err(err) = graphics::start_transition(&mut ctx, transition) {
+                        log::warn!("Could not start transition with error {}", err);
+                    }
+                }
+                state.end(&mut ctx, &mut userctx);
+                state = next.state;
+                state.start(&mut ctx, &mut userctx);
+            }
+
             if macroquad::prelude::is_quit_requested() || !ctx.running {
                 state.end(&mut ctx, &mut userctx);
                 break;